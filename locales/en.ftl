@@ -18,3 +18,5 @@ editor = Editor
 topic-article-title = { $number } article(s)
 
 topic-list = Topic list
+
+toggle-dark-mode = Toggle dark mode
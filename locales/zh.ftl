@@ -17,4 +17,6 @@ editor = 责任编辑
 
 topic-article-title = { $number } 篇文章
 
-topic-list = 话题列表
\ No newline at end of file
+topic-list = 话题列表
+
+toggle-dark-mode = 切换深色模式
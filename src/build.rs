@@ -1,6 +1,6 @@
 use std::{path::Path, sync::mpsc, time::Duration};
 
-use crate::{data, ZineEngine};
+use crate::{compress, data, images, ZineEngine};
 use anyhow::{Context, Result};
 use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode};
 use tokio::sync::broadcast::Sender;
@@ -9,6 +9,8 @@ pub async fn watch_build<P: AsRef<Path>>(
     source: P,
     dest: P,
     watch: bool,
+    compress: bool,
+    offline: bool,
     sender: Option<Sender<()>>,
 ) -> Result<()> {
     // Use zine.toml to find root path
@@ -19,8 +21,10 @@ pub async fn watch_build<P: AsRef<Path>>(
     // let dest = source.join(dest);
 
     data::load(&source);
+    data::write().set_offline(offline);
 
     let source_path = source.clone();
+    let export_path = source.clone();
     tokio::spawn(async move {
         tokio::signal::ctrl_c().await.unwrap();
         // Save zine data only when the process gonna exist
@@ -31,7 +35,7 @@ pub async fn watch_build<P: AsRef<Path>>(
     let mut engine = ZineEngine::new(source, dest, zine)?;
     // Spawn the build process as a blocking task, avoid starving other tasks.
     let build_result = tokio::task::spawn_blocking(move || {
-        build(&mut engine, false)?;
+        build(&mut engine, false, compress)?;
 
         if watch {
             println!("Watching...");
@@ -47,9 +51,19 @@ pub async fn watch_build<P: AsRef<Path>>(
                 watcher.watch(Path::new("static"), RecursiveMode::Recursive)?;
             }
 
+            // The cache directory and the downloaded preview images are written
+            // by the build itself, their changes never trigger a rebuild.
+            let ignored_dirs = [
+                engine.source.join(crate::ZINE_CACHE_DIR),
+                engine.source.join(images::PREVIEWS_DIR),
+            ];
             loop {
                 match rx.recv() {
-                    Ok(_) => match build(&mut engine, true) {
+                    Ok(Ok(events))
+                        if events.iter().all(|event| {
+                            ignored_dirs.iter().any(|dir| event.path.starts_with(dir))
+                        }) => {}
+                    Ok(_) => match build(&mut engine, true, compress) {
                         Ok(_) => {
                             if let Some(sender) = sender.as_ref() {
                                 sender.send(())?;
@@ -74,12 +88,17 @@ pub async fn watch_build<P: AsRef<Path>>(
         println!("Error: {}", &err);
         std::process::exit(1);
     }
+    // Save the zine data, such as the downloaded embed thumbnails.
+    data::export(export_path)?;
     Ok(())
 }
 
-fn build(engine: &mut ZineEngine, reload: bool) -> Result<()> {
+fn build(engine: &mut ZineEngine, reload: bool, compress: bool) -> Result<()> {
     let instant = std::time::Instant::now();
     engine.build(reload)?;
+    if compress {
+        compress::compress_dir(&engine.dest)?;
+    }
     println!("Build cost: {}ms", instant.elapsed().as_millis());
     Ok(())
 }
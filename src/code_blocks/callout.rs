@@ -1,58 +1,127 @@
 use std::collections::HashMap;
 use std::fmt::Write;
 
+use pulldown_cmark::escape::escape_html;
+
 use crate::data;
+use crate::entity::{CalloutTheme, MarkdownConfig};
 use crate::markdown::MarkdownRender;
 
 use super::CodeBlock;
 
-static DEFAULT_BG_COLOR: &str = "#e1eaff";
-static DEFAULT_BORDER_COLOR: &str = "#82a7fc";
+/// The GitHub-style admonition kinds, each kind is rendered with the callout theme of the same name.
+static ADMONITION_KINDS: &[&str] = &["note", "tip", "important", "warning", "caution"];
 
 /// The CalloutBlock to highlight some pragraphs.
 pub struct CalloutBlock<'a> {
-    bg_color: &'a str,
-    border_color: &'a str,
+    bg_color: String,
+    border_color: String,
+    title: Option<String>,
+    icon: Option<String>,
     content: &'a str,
 }
 
 impl<'a> CalloutBlock<'a> {
-    pub fn new(options: HashMap<String, &'a str>, block: &'a str) -> Self {
-        let (bg_color, border_color) = Self::parse_colors(&options);
+    pub fn new(
+        options: HashMap<String, &'a str>,
+        block: &'a str,
+        markdown_config: &MarkdownConfig,
+    ) -> Self {
+        let theme = Self::parse_theme(&options, markdown_config);
         CalloutBlock {
-            bg_color,
-            border_color,
+            bg_color: options
+                .get("bg_color")
+                .map(|color| color.to_string())
+                .unwrap_or(theme.bg),
+            border_color: options
+                .get("border_color")
+                .map(|color| color.to_string())
+                .unwrap_or(theme.border),
+            title: options
+                .get("title")
+                .map(|title| title.to_string())
+                .or(theme.title),
+            icon: theme.icon,
             content: block,
         }
     }
 
-    fn parse_colors(options: &HashMap<String, &'a str>) -> (&'a str, &'a str) {
-        let (bg_color, border_color) = (
-            options.get("bg_color").cloned(),
-            options.get("border_color").cloned(),
-        );
+    fn parse_theme(
+        options: &HashMap<String, &'a str>,
+        markdown_config: &MarkdownConfig,
+    ) -> CalloutTheme {
+        let default_theme = || {
+            markdown_config
+                .callout_theme(MarkdownConfig::DEFAULT_CALLOUT_THEME)
+                .expect("The default callout theme is builtin")
+        };
+        match options.get("theme").map(|theme| theme.to_lowercase()) {
+            Some(name) => markdown_config.callout_theme(&name).unwrap_or_else(|| {
+                println!("Warning: unknown callout theme `{name}`, fallback to the default theme");
+                default_theme()
+            }),
+            None => default_theme(),
+        }
+    }
+}
+
+/// The GitHub-style admonition, which is a blockquote starts with the kind marker.
+///
+/// ```markdown
+/// > [!WARNING]
+/// > Critical content demanding immediate user attention.
+/// ```
+///
+/// The supported kinds are `NOTE`, `TIP`, `IMPORTANT`, `WARNING` and `CAUTION`,
+/// the text after the marker overrides the default title.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Admonition<'a> {
+    kind: &'static str,
+    title: Option<&'a str>,
+    content: String,
+}
 
-        let (theme_bg_color, theme_border_color) =
-            match options.get("theme").map(|theme| theme.to_lowercase()) {
-                Some(theme) => {
-                    match theme.as_ref() {
-                        "grey" | "gray" => ("#dee0e399", "#dee0e3"),
-                        "red" => ("#fde2e2", "#f98e8b"),
-                        "orange" => ("#feead2", "#ffba6b"),
-                        "yellow" => ("#ffffcc", "#fff67a"),
-                        "green" => ("#d9f5d6", "#8ee085"),
-                        "purple" => ("#eceafe", "#ad82f7"),
-                        // Default is the blue theme.
-                        _ => (DEFAULT_BG_COLOR, DEFAULT_BORDER_COLOR),
-                    }
+impl<'a> Admonition<'a> {
+    /// Parse the admonition from the markdown source of the blockquote.
+    ///
+    /// The `depth` is the count of the outer blockquotes, whose `>` markers
+    /// are also the prefix of the continuation lines.
+    pub fn parse(blockquote: &'a str, depth: usize) -> Option<Self> {
+        let mut lines = blockquote.lines().enumerate().map(|(index, mut line)| {
+            // The source of the first line starts from the `>` of this blockquote.
+            let markers = if index == 0 { 1 } else { depth + 1 };
+            for _ in 0..markers {
+                // The lazy continuation line has less `>`.
+                match line.trim_start().strip_prefix('>') {
+                    Some(rest) => line = rest.strip_prefix(' ').unwrap_or(rest),
+                    None => break,
                 }
-                None => (DEFAULT_BG_COLOR, DEFAULT_BORDER_COLOR),
-            };
+            }
+            line
+        });
+        let (kind, title) = lines.next()?.trim().strip_prefix("[!")?.split_once(']')?;
+        let kind = ADMONITION_KINDS
+            .iter()
+            .find(|k| k.eq_ignore_ascii_case(kind))?;
+        let title = Some(title.trim()).filter(|title| !title.is_empty());
+        let content = lines.fold(String::new(), |mut content, line| {
+            content.push_str(line);
+            content.push('\n');
+            content
+        });
+        Some(Admonition {
+            kind,
+            title,
+            content,
+        })
+    }
 
-        (
-            bg_color.unwrap_or(theme_bg_color),
-            border_color.unwrap_or(theme_border_color),
-        )
+    pub fn render(&self, markdown_config: &MarkdownConfig) -> anyhow::Result<String> {
+        let mut options = HashMap::from([("theme".to_owned(), self.kind)]);
+        if let Some(title) = self.title {
+            options.insert("title".to_owned(), title);
+        }
+        CalloutBlock::new(options, &self.content, markdown_config).render()
     }
 }
 
@@ -64,6 +133,24 @@ impl<'a> CodeBlock for CalloutBlock<'a> {
             self.bg_color, self.border_color,
         );
         writeln!(&mut html, r#"<div class="callout" style="{}">"#, style)?;
+        if self.title.is_some() || self.icon.is_some() {
+            write!(&mut html, r#" <div class="callout-title">"#)?;
+            match self.icon.as_deref() {
+                Some(icon) if icon.trim_start().starts_with("<svg") => html.push_str(icon),
+                Some(icon) => {
+                    html.push_str(r#"<img class="callout-icon" src=""#);
+                    escape_html(&mut html, icon)?;
+                    html.push_str(r#"" alt="">"#);
+                }
+                None => {}
+            }
+            if let Some(title) = self.title.as_deref() {
+                html.push_str("<span>");
+                escape_html(&mut html, title)?;
+                html.push_str("</span>");
+            }
+            writeln!(&mut html, "</div>")?;
+        }
         let zine_data = data::read();
         let markdown_config = zine_data.get_markdown_config();
         let block_html = MarkdownRender::new(markdown_config).render_html(self.content);
@@ -75,20 +162,89 @@ impl<'a> CodeBlock for CalloutBlock<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::code_blocks::Fenced;
+    use crate::{
+        code_blocks::Fenced,
+        data,
+        entity::{CalloutTheme, MarkdownConfig},
+        markdown::MarkdownRender,
+    };
 
-    use super::CalloutBlock;
+    use super::{Admonition, CalloutBlock};
 
     #[test]
     fn test_parse_colors() {
+        let mut config = MarkdownConfig::default();
         let fenced = Fenced::parse("callout, theme: red").unwrap();
-        let callout = CalloutBlock::new(fenced.options, "dummy");
+        let callout = CalloutBlock::new(fenced.options, "dummy", &config);
         assert_eq!(callout.bg_color, "#fde2e2");
         assert_eq!(callout.border_color, "#f98e8b");
 
         let fenced = Fenced::parse("callout, theme: red, bg_color: #123456").unwrap();
-        let callout = CalloutBlock::new(fenced.options, "dummy");
+        let callout = CalloutBlock::new(fenced.options, "dummy", &config);
         assert_eq!(callout.bg_color, "#123456");
         assert_eq!(callout.border_color, "#f98e8b");
+
+        config.callout_themes.insert(
+            "red".to_owned(),
+            CalloutTheme {
+                bg: "#ff0000".to_owned(),
+                border: "#aa0000".to_owned(),
+                icon: None,
+                title: Some("Danger".to_owned()),
+            },
+        );
+        let fenced = Fenced::parse("callout, theme: Red").unwrap();
+        let callout = CalloutBlock::new(fenced.options, "dummy", &config);
+        assert_eq!(callout.bg_color, "#ff0000");
+        assert_eq!(callout.title.as_deref(), Some("Danger"));
+
+        let fenced = Fenced::parse("callout, theme: warning, title: Heads up").unwrap();
+        let callout = CalloutBlock::new(fenced.options, "dummy", &config);
+        assert_eq!(callout.title.as_deref(), Some("Heads up"));
+        assert!(callout.icon.is_some());
+    }
+
+    #[test]
+    fn test_parse_admonition() {
+        let admonition =
+            Admonition::parse("> [!Tip]\n> Use `zine serve`.\nLazy line.\n", 0).unwrap();
+        assert_eq!(
+            admonition,
+            Admonition {
+                kind: "tip",
+                title: None,
+                content: "Use `zine serve`.\nLazy line.\n".to_owned(),
+            }
+        );
+
+        let admonition = Admonition::parse("  >[!WARNING] Be careful\n>\n> > Nested\n", 0).unwrap();
+        assert_eq!(admonition.kind, "warning");
+        assert_eq!(admonition.title, Some("Be careful"));
+        assert_eq!(admonition.content, "\n> Nested\n");
+
+        // The source of the nested blockquote, the continuation lines have the outer `>`.
+        let admonition = Admonition::parse("> [!NOTE]\n> > Text\n> >\n> > > Quote\n", 1).unwrap();
+        assert_eq!(admonition.content, "Text\n\n> Quote\n");
+
+        assert!(Admonition::parse("> [!UNKNOWN]\n> Text\n", 0).is_none());
+        assert!(Admonition::parse("> Normal quote [!NOTE]\n", 0).is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_render_nested_admonition() {
+        let dir = tempfile::tempdir().unwrap();
+        data::load(dir.path());
+        let config = MarkdownConfig::default();
+        let markdown = "> Outer\n>\n> > [!NOTE]\n> > Inner *text*.\n> >\n> > > Quote\n>\n> Outer end\n\nAfter\n";
+        let html = MarkdownRender::new(&config).render_html(markdown);
+
+        // The admonition events are skipped until its blockquote ends.
+        assert!(html.starts_with("<blockquote>\n<p>Outer</p>\n<div class=\"callout\""));
+        assert!(
+            html.contains("<p>Inner <em>text</em>.</p>\n<blockquote>\n<p>Quote</p>\n</blockquote>")
+        );
+        assert_eq!(html.matches("<blockquote>").count(), 2);
+        assert!(!html.contains("[!NOTE]"));
+        assert!(html.ends_with("<p>Outer end</p>\n</blockquote>\n<p>After</p>\n"));
     }
 }
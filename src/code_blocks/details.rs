@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use pulldown_cmark::escape::escape_html;
+
+use crate::data;
+use crate::markdown::MarkdownRender;
+
+use super::CodeBlock;
+
+static DEFAULT_SUMMARY: &str = "Details";
+
+/// The DetailsBlock to render a collapsible section.
+///
+/// ````markdown
+/// ```details, summary: Show the answer, open
+/// The **answer** is 42.
+/// ```
+/// ````
+pub struct DetailsBlock<'a> {
+    summary: &'a str,
+    open: bool,
+    content: &'a str,
+}
+
+impl<'a> DetailsBlock<'a> {
+    pub fn new(options: &HashMap<String, &'a str>, block: &'a str) -> Self {
+        DetailsBlock {
+            summary: options.get("summary").copied().unwrap_or(DEFAULT_SUMMARY),
+            open: options.get("open").is_some_and(|open| *open == "true"),
+            content: block,
+        }
+    }
+}
+
+impl<'a> CodeBlock for DetailsBlock<'a> {
+    fn render(&self) -> anyhow::Result<String> {
+        let mut html = String::new();
+        let open = if self.open { " open" } else { "" };
+        write!(&mut html, r#"<details class="details"{open}><summary>"#)?;
+        escape_html(&mut html, self.summary)?;
+        writeln!(&mut html, "</summary>")?;
+        let zine_data = data::read();
+        let markdown_config = zine_data.get_markdown_config();
+        let block_html = MarkdownRender::new(markdown_config).render_html(self.content);
+        writeln!(
+            &mut html,
+            r#" <div class="details-content">{}</div>"#,
+            block_html
+        )?;
+        writeln!(&mut html, "</details>")?;
+        Ok(html)
+    }
+}
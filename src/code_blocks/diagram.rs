@@ -0,0 +1,125 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use anyhow::{anyhow, Result};
+use layout::{
+    backends::svg::SVGWriter,
+    gv::{DotParser, GraphBuilder},
+};
+use pulldown_cmark::escape::escape_html;
+
+use super::CodeBlock;
+
+static MERMAID_CONTAINER: &str = r#"<pre class="mermaid">"#;
+
+/// The DotBlock to render a Graphviz diagram to inline SVG at build time.
+pub struct DotBlock<'a>(pub &'a str);
+
+impl<'a> DotBlock<'a> {
+    fn render_svg(&self) -> Result<String> {
+        let mut parser = DotParser::new(self.0);
+        let graph = parser.process().map_err(|err| anyhow!(err))?;
+        // The layout engine may panic on some unsupported graphs.
+        let svg = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut builder = GraphBuilder::new();
+            builder.visit_graph(&graph);
+            let mut visual_graph = builder.get();
+            let mut writer = SVGWriter::new();
+            visual_graph.do_it(false, false, false, &mut writer);
+            writer.finalize()
+        }))
+        .map_err(|_| anyhow!("layout failed"))?;
+        // Strip the xml declaration, the svg is inlined into html.
+        Ok(match svg.find("<svg") {
+            Some(start) => svg[start..].to_owned(),
+            None => svg,
+        })
+    }
+}
+
+impl<'a> CodeBlock for DotBlock<'a> {
+    fn render(&self) -> Result<String> {
+        match self.render_svg() {
+            Ok(svg) => Ok(format!(r#"<div class="diagram">{svg}</div>"#)),
+            Err(err) => {
+                println!("Warning: render dot diagram failed: {err}");
+                let mut html = String::from(r#"<pre class="diagram-error">"#);
+                escape_html(&mut html, self.0)?;
+                html.push_str("</pre>");
+                Ok(html)
+            }
+        }
+    }
+}
+
+/// The MermaidBlock to render a Mermaid diagram in the browser.
+pub struct MermaidBlock<'a>(pub &'a str);
+
+impl<'a> CodeBlock for MermaidBlock<'a> {
+    fn render(&self) -> Result<String> {
+        let mut html = String::from(MERMAID_CONTAINER);
+        escape_html(&mut html, self.0)?;
+        html.push_str("</pre>");
+        Ok(html)
+    }
+}
+
+/// Inject the mermaid client script, imported from `script_url`,
+/// into the page if it has mermaid diagrams.
+pub fn inject_mermaid_script(html: &mut Vec<u8>, script_url: &str) {
+    let has_mermaid = html
+        .windows(MERMAID_CONTAINER.len())
+        .any(|window| window == MERMAID_CONTAINER.as_bytes());
+    if !has_mermaid {
+        return;
+    }
+
+    let body_end = html
+        .windows(b"</body>".len())
+        .rposition(|window| window == b"</body>")
+        .unwrap_or(html.len());
+    // The url is quoted as a JSON string, which is also a valid JS string literal.
+    let script = format!(
+        r#"<script type="module">
+    import mermaid from {url};
+    mermaid.initialize({{ startOnLoad: true }});
+</script>
+"#,
+        url = serde_json::to_string(script_url).unwrap_or_default()
+    );
+    html.splice(body_end..body_end, script.into_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inject_mermaid_script, DotBlock, MermaidBlock};
+    use crate::code_blocks::CodeBlock;
+
+    #[test]
+    fn test_render_dot() {
+        let html = DotBlock("digraph { a -> b; }").render().unwrap();
+        assert!(html.starts_with(r#"<div class="diagram"><svg "#));
+        assert!(html.ends_with("</svg></div>"));
+
+        let html = DotBlock("digraph { a -> }").render().unwrap();
+        assert_eq!(
+            html,
+            r#"<pre class="diagram-error">digraph { a -&gt; }</pre>"#
+        );
+    }
+
+    #[test]
+    fn test_inject_mermaid_script() {
+        let mut html = b"<body><p>a</p></body>".to_vec();
+        inject_mermaid_script(&mut html, "/static/mermaid.mjs");
+        assert_eq!(html, b"<body><p>a</p></body>");
+
+        let mermaid = MermaidBlock("graph TD\n  A-->B").render().unwrap();
+        assert_eq!(mermaid, "<pre class=\"mermaid\">graph TD\n  A--&gt;B</pre>");
+        let mut html = format!("<body>{mermaid}</body>").into_bytes();
+        inject_mermaid_script(&mut html, "/static/mermaid.mjs");
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains(r#"import mermaid from "/static/mermaid.mjs";"#));
+        assert!(html.contains("mermaid.initialize"));
+        assert!(html.ends_with("</script>\n</body>"));
+    }
+}
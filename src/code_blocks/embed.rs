@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use tera::Context;
+
+use crate::{data::UrlPreviewInfo, engine};
+
+use super::CodeBlock;
+
+pub(super) const YOUTUBE: &str = "youtube";
+pub(super) const VIMEO: &str = "vimeo";
+pub(super) const AUDIO: &str = "audio";
+pub(super) const VIDEO: &str = "video";
+pub(super) const GIST: &str = "gist";
+pub(super) const TWEET: &str = "tweet";
+
+/// The EmbedBlock to embed media in a privacy-friendly way.
+///
+/// The third-party players are rendered as click-to-load facades, nothing is
+/// loaded from the third-party until the reader clicks.
+///
+/// ````markdown
+/// ```youtube, start: 90, autoplay: false
+/// https://www.youtube.com/watch?v=dQw4w9WgXcQ
+/// ```
+/// ````
+#[derive(Debug, Serialize)]
+pub(super) struct EmbedBlock<'a> {
+    kind: &'a str,
+    // The original url.
+    url: String,
+    title: Option<String>,
+    description: Option<String>,
+    thumbnail: Option<String>,
+    // The iframe src or the media src.
+    src: Option<String>,
+    // The iframe srcdoc, such as the gist script.
+    srcdoc: Option<String>,
+    // The video poster.
+    poster: Option<&'a str>,
+    autoplay: bool,
+    #[serde(skip)]
+    preview_url: Option<String>,
+}
+
+impl<'a> EmbedBlock<'a> {
+    pub(super) fn parse(
+        kind: &'a str,
+        options: &HashMap<String, &'a str>,
+        block: &str,
+    ) -> Result<Self> {
+        let url = block.trim();
+        let start = options
+            .get("start")
+            .and_then(|start| start.parse::<u32>().ok());
+        // The facades load the player by clicking, it's reasonable to autoplay.
+        let autoplay = options
+            .get("autoplay")
+            .and_then(|v| v.parse::<bool>().ok())
+            .unwrap_or(!matches!(kind, AUDIO | VIDEO));
+
+        let mut embed = EmbedBlock {
+            kind,
+            url: url.to_owned(),
+            title: options.get("title").map(|title| title.to_string()),
+            description: None,
+            thumbnail: None,
+            src: None,
+            srcdoc: None,
+            poster: options.get("poster").copied(),
+            autoplay,
+            preview_url: None,
+        };
+        match kind {
+            YOUTUBE => {
+                let Some(id) = youtube_id(url) else {
+                    bail!("Invalid youtube url: {url}");
+                };
+                let mut src = format!(
+                    "https://www.youtube-nocookie.com/embed/{id}?autoplay={}",
+                    u8::from(autoplay)
+                );
+                if let Some(start) = start {
+                    src.push_str(&format!("&start={start}"));
+                }
+                embed.url = format!("https://www.youtube.com/watch?v={id}");
+                embed.src = Some(src);
+                embed.preview_url = Some(embed.url.clone());
+            }
+            VIMEO => {
+                let Some(id) = last_segment(url).filter(|id| is_digits(id)) else {
+                    bail!("Invalid vimeo url: {url}");
+                };
+                let mut src = format!(
+                    "https://player.vimeo.com/video/{id}?dnt=1&autoplay={}",
+                    u8::from(autoplay)
+                );
+                if let Some(start) = start {
+                    src.push_str(&format!("#t={start}s"));
+                }
+                embed.url = format!("https://vimeo.com/{id}");
+                embed.src = Some(src);
+                embed.preview_url = Some(embed.url.clone());
+            }
+            TWEET => {
+                let Some(id) = url
+                    .split_once("/status/")
+                    .and_then(|(_, rest)| rest.split(['?', '/']).next())
+                    .filter(|id| is_digits(id))
+                else {
+                    bail!("Invalid tweet url: {url}");
+                };
+                embed.src = Some(format!(
+                    "https://platform.twitter.com/embed/Tweet.html?dnt=true&id={id}"
+                ));
+                embed.preview_url = Some(url.to_owned());
+            }
+            GIST => {
+                if !url.starts_with("https://gist.github.com/") {
+                    bail!("Invalid gist url: {url}");
+                }
+                let script = url.split(['?', '#']).next().unwrap_or(url);
+                // The gist script uses `document.write()`, it must run in its own document.
+                embed.srcdoc = Some(format!(
+                    r#"<base target="_parent"><script src="{script}.js"></script>"#
+                ));
+                embed.preview_url = Some(url.to_owned());
+            }
+            AUDIO | VIDEO => {
+                let mut src = url.to_owned();
+                if let Some(start) = start {
+                    // The media fragment to start playing at.
+                    src.push_str(&format!("#t={start}"));
+                }
+                embed.src = Some(src);
+            }
+            _ => bail!("Unsupported embed: {kind}"),
+        }
+        Ok(embed)
+    }
+
+    /// The page url to preview the title and thumbnail, `None` if no need to preview.
+    pub(super) fn preview_url(&self) -> Option<&str> {
+        self.preview_url.as_deref()
+    }
+
+    pub(super) fn set_preview(&mut self, info: UrlPreviewInfo) {
+        if self.title.is_none() && !info.title.is_empty() {
+            self.title = Some(info.title);
+        }
+        if !info.description.is_empty() {
+            self.description = Some(info.description);
+        }
+        // Only the downloaded thumbnail, the remote one would
+        // send the readers to the third-party before clicking.
+        self.thumbnail = info.local_image.filter(|image| !image.is_empty());
+    }
+}
+
+impl<'a> CodeBlock for EmbedBlock<'a> {
+    fn render(&self) -> Result<String> {
+        let mut context = Context::new();
+        context.insert("embed", &self);
+        let html = engine::get_tera().render("blocks/embed.jinja", &context)?;
+        Ok(html)
+    }
+}
+
+// Parse the youtube video id from these urls:
+// - https://www.youtube.com/watch?v={id}
+// - https://youtu.be/{id}
+// - https://www.youtube.com/embed/{id}
+// - https://www.youtube.com/shorts/{id}
+// - {id}
+fn youtube_id(url: &str) -> Option<&str> {
+    let id = if let Some((_, query)) = url.split_once('?') {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("v="))
+            .or_else(|| last_segment(url))?
+    } else {
+        last_segment(url)?
+    };
+    let is_valid = id.len() == 11
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    is_valid.then_some(id)
+}
+
+// The last path segment of the url, without query and fragment.
+fn last_segment(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|segment| !segment.is_empty())
+}
+
+fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use test_case::test_case;
+
+    use super::{youtube_id, EmbedBlock};
+    use crate::{code_blocks::CodeBlock, data::UrlPreviewInfo};
+
+    #[test_case("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1s", Some("dQw4w9WgXcQ"); "watch")]
+    #[test_case("https://youtu.be/dQw4w9WgXcQ", Some("dQw4w9WgXcQ"); "short link")]
+    #[test_case("https://www.youtube.com/embed/dQw4w9WgXcQ?start=2", Some("dQw4w9WgXcQ"); "embed")]
+    #[test_case("https://www.youtube.com/shorts/dQw4w9WgXcQ", Some("dQw4w9WgXcQ"); "shorts")]
+    #[test_case("dQw4w9WgXcQ", Some("dQw4w9WgXcQ"); "id")]
+    #[test_case("https://www.youtube.com/", None; "invalid")]
+    fn test_youtube_id(url: &str, expected: Option<&str>) {
+        assert_eq!(youtube_id(url), expected);
+    }
+
+    #[test]
+    fn test_parse_embed() {
+        let options = HashMap::from([("start".to_owned(), "90"), ("autoplay".to_owned(), "false")]);
+        let embed =
+            EmbedBlock::parse("youtube", &options, "https://youtu.be/dQw4w9WgXcQ\n").unwrap();
+        assert_eq!(
+            embed.src.as_deref(),
+            Some("https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ?autoplay=0&start=90")
+        );
+        assert_eq!(
+            embed.preview_url(),
+            Some("https://www.youtube.com/watch?v=dQw4w9WgXcQ")
+        );
+
+        let embed =
+            EmbedBlock::parse("vimeo", &HashMap::new(), "https://vimeo.com/76979871").unwrap();
+        assert_eq!(
+            embed.src.as_deref(),
+            Some("https://player.vimeo.com/video/76979871?dnt=1&autoplay=1")
+        );
+
+        let embed = EmbedBlock::parse(
+            "tweet",
+            &HashMap::new(),
+            "https://twitter.com/rustlang/status/1580219434543382528?s=20",
+        )
+        .unwrap();
+        assert_eq!(
+            embed.src.as_deref(),
+            Some("https://platform.twitter.com/embed/Tweet.html?dnt=true&id=1580219434543382528")
+        );
+
+        let embed = EmbedBlock::parse("video", &options, "/static/demo.mp4").unwrap();
+        assert_eq!(embed.src.as_deref(), Some("/static/demo.mp4#t=90"));
+        assert!(embed.preview_url().is_none());
+        assert!(!embed.autoplay);
+
+        assert!(EmbedBlock::parse("gist", &HashMap::new(), "https://github.com/zineland").is_err());
+        assert!(EmbedBlock::parse("vimeo", &HashMap::new(), "https://vimeo.com/abc").is_err());
+    }
+
+    #[test]
+    fn test_render_embed() {
+        crate::engine::init_test_tera();
+        let mut embed =
+            EmbedBlock::parse("youtube", &HashMap::new(), "https://youtu.be/dQw4w9WgXcQ").unwrap();
+        // Never hotlink the remote thumbnail.
+        embed.set_preview(UrlPreviewInfo {
+            title: "Video".into(),
+            image: Some("https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg".into()),
+            ..Default::default()
+        });
+        let html = embed.render().unwrap();
+        assert!(!html.contains("ytimg.com"));
+        assert!(!html.contains("embed-thumbnail"));
+
+        embed.set_preview(UrlPreviewInfo {
+            title: "Video".into(),
+            image: Some("https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg".into()),
+            local_image: Some("/static/previews/0123456789abcdef.jpg".into()),
+            ..Default::default()
+        });
+        let html = embed.render().unwrap();
+        assert!(!html.contains("ytimg.com"));
+        assert!(html.contains(r#"src="&#x2F;static&#x2F;previews&#x2F;0123456789abcdef.jpg""#));
+
+        let options = HashMap::from([("poster".to_owned(), r#"/a.png" onerror="alert(1)"#)]);
+        let embed = EmbedBlock::parse("video", &options, r#"/demo.mp4?a=1&b="2""#).unwrap();
+        let html = embed.render().unwrap();
+        assert!(html.contains(r#"src="&#x2F;demo.mp4?a=1&amp;b=&quot;2&quot;""#));
+        assert!(html.contains(r#"poster="&#x2F;a.png&quot; onerror=&quot;alert(1)""#));
+    }
+}
@@ -0,0 +1,15 @@
+use crate::markdown::render_math;
+
+use super::CodeBlock;
+
+/// The MathBlock to render a display math formula.
+pub struct MathBlock<'a>(pub &'a str);
+
+impl<'a> CodeBlock for MathBlock<'a> {
+    fn render(&self) -> anyhow::Result<String> {
+        Ok(format!(
+            r#"<div class="math-block">{}</div>"#,
+            render_math(self.0, true)
+        ))
+    }
+}
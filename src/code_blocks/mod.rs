@@ -1,29 +1,71 @@
 use std::collections::HashMap;
 
 use anyhow::{bail, Result};
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag};
 
 mod author;
 mod callout;
+mod details;
+mod diagram;
+mod embed;
 mod inline_link;
+mod math;
 mod quote;
+mod tabs;
+mod template;
 mod url_preview;
 
-use crate::data::{self, PreviewEvent};
+use crate::{
+    data::{self, PreviewEvent, UrlPreviewInfo},
+    images,
+};
 pub use author::AuthorCode;
+pub use callout::Admonition;
+pub use diagram::inject_mermaid_script;
 pub use inline_link::InlineLink;
+pub use template::{is_template_block, register_template_blocks};
 use url_preview::{UrlPreviewBlock, UrlPreviewError};
 
-use self::{callout::CalloutBlock, quote::QuoteBlock};
+use self::{
+    callout::CalloutBlock,
+    details::DetailsBlock,
+    diagram::{DotBlock, MermaidBlock},
+    embed::{EmbedBlock, AUDIO, GIST, TWEET, VIDEO, VIMEO, YOUTUBE},
+    math::MathBlock,
+    quote::QuoteBlock,
+    tabs::TabsBlock,
+    template::TemplateBlock,
+};
 
 pub trait CodeBlock {
     fn render(&self) -> Result<String>;
 }
 
 const CALLOUT: &str = "callout";
+const DETAILS: &str = "details";
+const DOT: &str = "dot";
+const MATH: &str = "math";
+const MERMAID: &str = "mermaid";
 const QUOTE: &str = "quote";
+const TABS: &str = "tabs";
 const URL_PREVIEW: &str = "urlpreview";
 
-const ALL_CODE_BLOCKS: &[&str] = &[CALLOUT, QUOTE, URL_PREVIEW];
+const ALL_CODE_BLOCKS: &[&str] = &[
+    AUDIO,
+    CALLOUT,
+    DETAILS,
+    DOT,
+    GIST,
+    MATH,
+    MERMAID,
+    QUOTE,
+    TABS,
+    TWEET,
+    URL_PREVIEW,
+    VIDEO,
+    VIMEO,
+    YOUTUBE,
+];
 
 #[derive(Debug, Default, PartialEq, Eq)]
 pub struct Fenced<'a> {
@@ -38,59 +80,115 @@ impl<'a> Fenced<'a> {
     }
 
     pub fn is_custom_code_block(&self) -> bool {
-        ALL_CODE_BLOCKS.contains(&self.name)
+        ALL_CODE_BLOCKS.contains(&self.name) || is_template_block(self.name)
     }
 
     /// Render code block. Return rendered HTML string if success,
     /// otherwise return URL preview error HTML string to remind user we have error.
     ///
     /// If the fenced is unsupported, we simply return `None`.
-    pub async fn render_code_block(self, block: &'a str) -> Option<String> {
+    ///
+    /// The `index` is the order of the block in the rendered markdown,
+    /// which keeps the ids of the identical blocks unique.
+    pub async fn render_code_block(self, block: &'a str, index: usize) -> Option<String> {
         match self.name {
             URL_PREVIEW => {
                 let url = block.trim();
-
-                let (first_preview, mut rx) = {
-                    // parking_lot RwLock guard isn't async-aware,
-                    // we should keep this guard drop in this scope.
-                    let data = data::read();
-                    if let Some(info) = data.get_preview(url) {
-                        let html = UrlPreviewBlock::new(self.options, url, info)
-                            .render()
-                            .unwrap();
-                        return Some(html);
-                    }
-
-                    data.preview_url(url)
-                };
-                rx.changed()
-                    .await
-                    .expect("URL preview watch channel receive failed.");
-                let event = rx.borrow();
-                match event.to_owned().expect("Url preview didn't initialized.") {
-                    PreviewEvent::Finished(info) => {
+                match preview(url, images::is_preview_download_enabled()).await {
+                    Ok(Some(info)) => {
                         let html = UrlPreviewBlock::new(self.options, url, info)
                             .render()
                             .unwrap();
-                        if first_preview {
-                            println!("URL previewed: {url}");
-                        }
                         Some(html)
                     }
-                    PreviewEvent::Failed(err) => {
-                        // Return a preview error block.
-                        Some(UrlPreviewError(url, &err).render().unwrap())
+                    // Render a plain link card for the uncached preview in offline mode.
+                    Ok(None) => {
+                        let info = UrlPreviewBlock::link_info(url);
+                        Some(
+                            UrlPreviewBlock::new(self.options, url, info)
+                                .render()
+                                .unwrap(),
+                        )
+                    }
+                    // Render the overridden info if the preview failed.
+                    Err(err) if UrlPreviewBlock::has_override(&self.options) => {
+                        println!("Warning: preview `{url}` failed: {err}");
+                        let info = UrlPreviewBlock::link_info(url);
+                        Some(
+                            UrlPreviewBlock::new(self.options, url, info)
+                                .render()
+                                .unwrap(),
+                        )
+                    }
+                    // Return a preview error block.
+                    Err(err) => Some(UrlPreviewError(url, &err).render().unwrap()),
+                }
+            }
+            YOUTUBE | VIMEO | AUDIO | VIDEO | GIST | TWEET => {
+                let mut embed = match EmbedBlock::parse(self.name, &self.options, block) {
+                    Ok(embed) => embed,
+                    Err(err) => {
+                        println!("Warning: {err}");
+                        return None;
+                    }
+                };
+                if let Some(url) = embed.preview_url().map(str::to_owned) {
+                    // The embed is still usable without the title and thumbnail,
+                    // which is downloaded to avoid hotlinking the third-party.
+                    match preview(&url, true).await {
+                        Ok(Some(info)) => embed.set_preview(info),
+                        Ok(None) => {}
+                        Err(err) => println!("Warning: preview `{url}` failed: {err}"),
                     }
                 }
+                Some(embed.render().unwrap())
             }
             CALLOUT => {
-                let html = CalloutBlock::new(self.options, block).render().unwrap();
+                let callout = {
+                    let zine_data = data::read();
+                    CalloutBlock::new(self.options, block, zine_data.get_markdown_config())
+                };
+                Some(callout.render().unwrap())
+            }
+            DETAILS => {
+                let html = DetailsBlock::new(&self.options, block).render().unwrap();
+                Some(html)
+            }
+            TABS => match TabsBlock::parse(block, index) {
+                Ok(tabs) => Some(tabs.render().unwrap()),
+                Err(err) => {
+                    println!("Warning: {err}");
+                    None
+                }
+            },
+            DOT => {
+                let html = DotBlock(block).render().unwrap();
+                Some(html)
+            }
+            MERMAID => {
+                let html = MermaidBlock(block).render().unwrap();
                 Some(html)
             }
-            QUOTE => {
-                let html = QuoteBlock::parse(block).unwrap().render().unwrap();
+            MATH => {
+                let html = MathBlock(block).render().unwrap();
                 Some(html)
             }
+            QUOTE => match QuoteBlock::parse(block) {
+                Ok(quote) => Some(quote.render().unwrap()),
+                Err(err) => {
+                    println!("Warning: {err}");
+                    None
+                }
+            },
+            name if is_template_block(name) => {
+                match TemplateBlock::new(name, &self.options, block).render() {
+                    Ok(html) => Some(html),
+                    Err(err) => {
+                        println!("Warning: render `{name}` block failed: {err:#}");
+                        None
+                    }
+                }
+            }
             _ => None,
         }
     }
@@ -106,17 +204,18 @@ impl<'a> Fenced<'a> {
             Some(name) if !name.is_empty() => {
                 let options = raw
                     .into_iter()
-                    .filter_map(|pair| {
-                        let mut v = pair.split(':').take(2);
-                        match (v.next(), v.next()) {
-                            (Some(key), Some(value)) => {
-                                // Replace key's dash to underscore.
-                                Some((key.trim().replace('-', "_"), value.trim()))
-                            }
-                            _ => {
-                                println!("Warning: invalid fenced options: {}", pair);
-                                None
-                            }
+                    .filter_map(|pair| match pair.split_once(':') {
+                        Some((key, value)) => {
+                            // Replace key's dash to underscore.
+                            Some((key.trim().replace('-', "_"), value.trim()))
+                        }
+                        // A single word option is a flag, such as `linenos`.
+                        None if is_flag(pair.trim()) => {
+                            Some((pair.trim().replace('-', "_"), "true"))
+                        }
+                        None => {
+                            println!("Warning: invalid fenced options: {}", pair);
+                            None
                         }
                     })
                     .collect::<HashMap<_, _>>();
@@ -129,10 +228,129 @@ impl<'a> Fenced<'a> {
     }
 }
 
+/// Preview the url, the preview info is cached in the zine data.
+///
+/// The image of the preview is downloaded if `download_image` is true.
+/// Return `None` if the url has no cached preview in offline mode.
+async fn preview(url: &str, download_image: bool) -> Result<Option<UrlPreviewInfo>, String> {
+    let (first_preview, mut rx) = {
+        // parking_lot RwLock guard isn't async-aware,
+        // we should keep this guard drop in this scope.
+        let data = data::read();
+        match data.get_preview(url) {
+            // Never touch the network in offline mode.
+            Some(info) if data.is_offline() || !info.needs_image_download(download_image) => {
+                return Ok(Some(info))
+            }
+            None if data.is_offline() => return Ok(None),
+            _ => {}
+        }
+
+        data.preview_url(url, download_image)
+    };
+    rx.changed()
+        .await
+        .expect("URL preview watch channel receive failed.");
+    let event = rx.borrow().to_owned();
+    match event.expect("Url preview didn't initialized.") {
+        PreviewEvent::Finished(info) => {
+            if first_preview {
+                println!("URL previewed: {url}");
+            }
+            Ok(Some(info))
+        }
+        PreviewEvent::Failed(err) => Err(err),
+    }
+}
+
+/// Collect the urls to preview of the code blocks in the markdown, and whether
+/// to download their images, such as the thumbnails of the embeds.
+///
+/// The code blocks nested in the callout, details and tabs blocks are included.
+pub fn preview_urls(markdown: &str) -> Vec<(String, bool)> {
+    let mut urls = Vec::new();
+    let mut fenced = None;
+    let mut block = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                fenced = Some(info);
+                block.clear();
+            }
+            Event::Text(text) if fenced.is_some() => block.push_str(&text),
+            Event::End(Tag::CodeBlock(_)) => {
+                let Some(info) = fenced.take() else {
+                    continue;
+                };
+                let Ok(fenced) = Fenced::parse(&info) else {
+                    continue;
+                };
+                match fenced.name {
+                    URL_PREVIEW => urls.push((
+                        block.trim().to_owned(),
+                        images::is_preview_download_enabled(),
+                    )),
+                    YOUTUBE | VIMEO | AUDIO | VIDEO | GIST | TWEET => {
+                        if let Some(url) = EmbedBlock::parse(fenced.name, &fenced.options, &block)
+                            .ok()
+                            .and_then(|embed| embed.preview_url().map(str::to_owned))
+                        {
+                            urls.push((url, true));
+                        }
+                    }
+                    CALLOUT | DETAILS | TABS => urls.extend(preview_urls(&block)),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    urls
+}
+
+fn is_flag(option: &str) -> bool {
+    !option.is_empty() && !option.contains(char::is_whitespace)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_preview_urls() {
+        let markdown = r#"
+```urlpreview
+https://zineland.io
+```
+
+````details, summary: More
+```urlpreview
+https://github.com/zineland/zine
+```
+````
+
+```youtube
+https://youtu.be/dQw4w9WgXcQ
+```
+
+```rust
+fn main() {}
+```
+"#;
+        assert_eq!(
+            preview_urls(markdown),
+            [
+                ("https://zineland.io".to_owned(), false),
+                ("https://github.com/zineland/zine".to_owned(), false),
+                // The thumbnail of the embed is downloaded.
+                (
+                    "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_owned(),
+                    true
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn test_fenced_parsing() {
         assert_eq!(Fenced::parse("").unwrap(), Fenced::empty());
@@ -159,5 +377,13 @@ mod tests {
 
         let fenced = Fenced::parse("rust").unwrap();
         assert!(!fenced.is_custom_code_block());
+
+        let fenced =
+            Fenced::parse("rust, linenos, hl-lines: 3-5 8, title: https://zineland.io/main.rs")
+                .unwrap();
+        let options = fenced.options;
+        assert_eq!(options["linenos"], "true");
+        assert_eq!(options["hl_lines"], "3-5 8");
+        assert_eq!(options["title"], "https://zineland.io/main.rs");
     }
 }
@@ -1,34 +1,127 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use serde::{Deserialize, Serialize};
 use tera::Context;
+use time::Date;
 
-use crate::engine;
+use crate::{data, engine};
 
 use super::CodeBlock;
 
+// The delimiter of the TOML front matter.
+static FRONT_MATTER_DELIMITER: &str = "+++";
+
+/// The QuoteBlock to render a quote with its author.
+///
+/// The quote is written in TOML, or in markdown with the TOML front matter:
+///
+/// ````markdown
+/// ```quote
+/// +++
+/// author_id = "alice"
+/// source = "https://zineland.io"
+/// source_title = "Zine"
+/// date = "2023-01-01"
+/// layout = "testimonial"
+/// +++
+/// The quote content in **markdown**.
+/// ```
+/// ````
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct QuoteBlock {
+    // The author id declared in the `[authors]` table, which provides
+    // the default author name, bio and avatar.
+    pub author_id: Option<String>,
     // The author name.
     // Plain text format.
     pub author: Option<String>,
     // The profile of the author.
     // Markdown format.
     pub bio: Option<String>,
+    // The avatar url of the author.
+    pub avatar: Option<String>,
+    // The source url of the quote.
+    pub source: Option<String>,
+    // The title of the source, fallback to the source url.
+    pub source_title: Option<String>,
+    #[serde(default, with = "crate::helpers::serde_date::option")]
+    pub date: Option<Date>,
+    #[serde(default)]
+    pub layout: QuoteLayout,
     // The comment content.
     // Markdown format.
+    #[serde(default)]
     pub content: String,
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuoteLayout {
+    #[default]
+    Default,
+    /// The large quote pulled out from the article.
+    PullQuote,
+    /// The testimonial card with the author avatar.
+    Testimonial,
+}
+
 impl QuoteBlock {
     pub fn parse(block: &str) -> Result<Self> {
-        match toml::from_str::<Self>(block) {
-            Ok(quote_block) => Ok(quote_block),
+        let mut quote_block = match split_front_matter(block) {
+            Some((front_matter, content)) => {
+                let mut quote_block = toml::from_str::<Self>(front_matter)
+                    .map_err(|error| anyhow!("Parse quote block front matter error: {}", error))?;
+                if !quote_block.content.is_empty() {
+                    bail!("The quote content should be written after the front matter");
+                }
+                quote_block.content = content.trim().to_owned();
+                quote_block
+            }
             // Parse failed if the block has invalid toml syntax.
-            Err(error) => Err(anyhow!("Parse quote block error: {}", error)),
+            None => toml::from_str::<Self>(block)
+                .map_err(|error| anyhow!("Parse quote block error: {}", error))?,
+        };
+        if quote_block.content.trim().is_empty() {
+            bail!("The quote block has no content");
+        }
+        if quote_block.source_title.is_some() && quote_block.source.is_none() {
+            bail!("The `source_title` of quote block requires the `source` url");
+        }
+        if let Some(author_id) = quote_block.author_id.take() {
+            quote_block.resolve_author(&author_id)?;
         }
+        Ok(quote_block)
+    }
+
+    // Fill the missing author info with the declared author.
+    fn resolve_author(&mut self, author_id: &str) -> Result<()> {
+        let data = data::read();
+        let Some(author) = data.get_author_by_id(author_id) else {
+            bail!("The author `{author_id}` of quote block is not declared in the [authors] table");
+        };
+        self.author_id = Some(author.id.clone());
+        self.author = self.author.take().or_else(|| author.name.clone());
+        self.bio = self.bio.take().or_else(|| author.bio.clone());
+        self.avatar = self.avatar.take().or_else(|| author.avatar.clone());
+        Ok(())
     }
 }
 
+// Split the `+++` delimited front matter and the markdown content.
+fn split_front_matter(block: &str) -> Option<(&str, &str)> {
+    let block = block.trim_start();
+    let rest = block
+        .strip_prefix(FRONT_MATTER_DELIMITER)?
+        .strip_prefix(['\n', '\r'])?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == FRONT_MATTER_DELIMITER {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
 impl CodeBlock for QuoteBlock {
     fn render(&self) -> Result<String> {
         let mut context = Context::new();
@@ -37,3 +130,46 @@ impl CodeBlock for QuoteBlock {
         Ok(html)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{split_front_matter, QuoteBlock, QuoteLayout};
+
+    #[test]
+    fn test_split_front_matter() {
+        assert_eq!(
+            split_front_matter("\n+++\nauthor = \"A\"\n+++\nHello\n+++\n"),
+            Some(("author = \"A\"\n", "Hello\n+++\n"))
+        );
+        assert_eq!(split_front_matter("content = \"+++\""), None);
+        assert_eq!(split_front_matter("+++\nauthor = \"A\"\n"), None);
+    }
+
+    #[test]
+    fn test_parse_quote() {
+        let quote = QuoteBlock::parse(
+            r#"
++++
+author = "Alice"
+source = "https://zineland.io"
+date = "2023-01-02"
+layout = "pull-quote"
++++
+The **quote**.
+"#,
+        )
+        .unwrap();
+        assert_eq!(quote.author.as_deref(), Some("Alice"));
+        assert_eq!(quote.content, "The **quote**.");
+        assert_eq!(quote.layout, QuoteLayout::PullQuote);
+        assert_eq!(quote.date.unwrap().to_string(), "2023-01-02");
+
+        let quote = QuoteBlock::parse("author = \"Bob\"\ncontent = \"Hi\"").unwrap();
+        assert_eq!(quote.layout, QuoteLayout::Default);
+
+        assert!(QuoteBlock::parse("author = \"Bob\"").is_err());
+        assert!(QuoteBlock::parse("content = \"Hi\"\nlayout = \"card\"").is_err());
+        assert!(QuoteBlock::parse("content = \"Hi\"\nsource_title = \"Zine\"").is_err());
+        assert!(QuoteBlock::parse("+++\ncontent = \"Hi\"\n+++\nHello").is_err());
+    }
+}
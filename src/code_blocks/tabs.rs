@@ -0,0 +1,169 @@
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+use pulldown_cmark::escape::escape_html;
+
+use crate::{data, helpers, markdown::MarkdownRender};
+
+use super::CodeBlock;
+
+// The line starts a tab, such as `=== Rust`.
+static TAB_MARKER: &str = "===";
+
+/// The TabsBlock to render the titled markdown sections as a tab group.
+///
+/// `````markdown
+/// ````tabs
+/// === Rust
+/// ```rust
+/// println!("Hello");
+/// ```
+/// === Python
+/// ```python
+/// print("Hello")
+/// ```
+/// ````
+/// `````
+pub struct TabsBlock<'a> {
+    // The id prefix of the tabs and panels, such as `tabs-{hash}-{index}`.
+    id: String,
+    tabs: Vec<(&'a str, String)>,
+}
+
+impl<'a> TabsBlock<'a> {
+    /// Parse the tabs block, the `index` is the order of the block in the markdown.
+    pub fn parse(block: &'a str, index: usize) -> Result<Self> {
+        let mut tabs: Vec<(&str, String)> = Vec::new();
+        // The `===` inside the nested code blocks isn't a tab.
+        let mut fence: Option<&str> = None;
+        for line in block.lines() {
+            match (fence, fence_marker(line)) {
+                // The closing fence has no info string.
+                (Some(opening), Some(marker))
+                    if marker.starts_with(opening) && line.trim() == marker =>
+                {
+                    fence = None
+                }
+                (Some(_), _) => {}
+                (None, Some(marker)) => fence = Some(marker),
+                (None, None) => {
+                    if let Some(title) = line.strip_prefix(TAB_MARKER) {
+                        let title = title.trim().trim_matches('"');
+                        if title.is_empty() {
+                            bail!("The tab title is missing: {line}");
+                        }
+                        tabs.push((title, String::new()));
+                        continue;
+                    }
+                }
+            }
+
+            match tabs.last_mut() {
+                Some((_, content)) => {
+                    content.push_str(line);
+                    content.push('\n');
+                }
+                None if line.trim().is_empty() => {}
+                None => bail!("The tabs content must start with a `=== <title>` line"),
+            }
+        }
+        if tabs.is_empty() {
+            bail!("The tabs block has no tab");
+        }
+
+        Ok(TabsBlock {
+            id: format!(
+                "tabs-{}-{index}",
+                &helpers::content_hash(block.as_bytes())[..8]
+            ),
+            tabs,
+        })
+    }
+}
+
+impl<'a> CodeBlock for TabsBlock<'a> {
+    fn render(&self) -> Result<String> {
+        let zine_data = data::read();
+        let markdown_config = zine_data.get_markdown_config();
+
+        let mut html = String::new();
+        writeln!(&mut html, r#"<div class="tabs">"#)?;
+        writeln!(&mut html, r#" <div class="tabs-list" role="tablist">"#)?;
+        for (index, (title, _)) in self.tabs.iter().enumerate() {
+            let id = &self.id;
+            let selected = index == 0;
+            write!(
+                &mut html,
+                r#"  <button type="button" class="tabs-tab" role="tab" id="{id}-tab-{index}" aria-controls="{id}-panel-{index}" aria-selected="{selected}" tabindex="{}">"#,
+                if selected { 0 } else { -1 },
+            )?;
+            escape_html(&mut html, title)?;
+            writeln!(&mut html, "</button>")?;
+        }
+        writeln!(&mut html, r#" </div>"#)?;
+        for (index, (_, content)) in self.tabs.iter().enumerate() {
+            let id = &self.id;
+            let hidden = if index == 0 { "" } else { " hidden" };
+            let content_html = MarkdownRender::new(markdown_config).render_html(content);
+            writeln!(
+                &mut html,
+                r#" <div class="tabs-panel" role="tabpanel" id="{id}-panel-{index}" aria-labelledby="{id}-tab-{index}" tabindex="0"{hidden}>{content_html}</div>"#,
+            )?;
+        }
+        writeln!(&mut html, r#"</div>"#)?;
+        Ok(html)
+    }
+}
+
+// The fence marker of the code block line, such as "```" and "~~~~".
+fn fence_marker(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let c = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.len() - line.trim_start_matches(c).len();
+    (len >= 3).then(|| &line[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::TabsBlock;
+    use crate::{data, entity::MarkdownConfig, markdown::MarkdownRender};
+
+    #[test]
+    fn test_parse_tabs() {
+        let block = "\n=== Rust\n````rust\n```a\n=== not a tab\n````\n=== \"Python\"\n*hi*\n";
+        let tabs = TabsBlock::parse(block, 1).unwrap();
+        assert_eq!(
+            tabs.tabs,
+            [
+                ("Rust", "````rust\n```a\n=== not a tab\n````\n".to_owned()),
+                ("Python", "*hi*\n".to_owned())
+            ]
+        );
+        assert!(tabs.id.starts_with("tabs-") && tabs.id.ends_with("-1"));
+
+        assert!(TabsBlock::parse("Orphan content\n=== Rust\n", 1).is_err());
+        assert!(TabsBlock::parse("===\n", 1).is_err());
+        assert!(TabsBlock::parse("\n", 1).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_render_identical_tabs() {
+        let dir = tempfile::tempdir().unwrap();
+        data::load(dir.path());
+        let config = MarkdownConfig::default();
+        let group = "````tabs\n=== Cargo\ncargo install zine\n=== Brew\nbrew install zine\n````\n";
+        let html = MarkdownRender::new(&config).render_html(&format!("{group}\n{group}"));
+
+        let ids = html
+            .split(r#" id=""#)
+            .skip(1)
+            .filter_map(|part| part.split('"').next())
+            .collect::<Vec<_>>();
+        assert_eq!(ids.len(), 8);
+        // The repeated tab groups never control the panels of each other.
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+        assert!(html.contains(r#"aria-controls="tabs-"#));
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use anyhow::{Context as _, Result};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use tera::Context;
+
+use crate::{data, engine, markdown::MarkdownRender};
+
+use super::{CodeBlock, ALL_CODE_BLOCKS};
+
+// The user-defined blocks and their body formats.
+static TEMPLATE_BLOCKS: Lazy<RwLock<HashMap<String, BodyFormat>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// How the block body is passed to the template.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BodyFormat {
+    /// The raw text.
+    #[default]
+    Raw,
+    /// The parsed TOML table.
+    Toml,
+    /// The rendered markdown html.
+    Markdown,
+}
+
+impl BodyFormat {
+    // Parse the declaration comment at the beginning of the template,
+    // such as `{# body: toml #}`.
+    fn from_template(template: &str) -> Self {
+        let declaration = template
+            .trim_start()
+            .strip_prefix("{#")
+            .and_then(|comment| comment.split_once("#}"))
+            .and_then(|(comment, _)| comment.trim().strip_prefix("body:"));
+        match declaration.map(str::trim) {
+            Some("toml") => BodyFormat::Toml,
+            Some("markdown") => BodyFormat::Markdown,
+            Some("raw") | None => BodyFormat::Raw,
+            Some(format) => {
+                println!("Warning: unknown block body format `{format}`, fallback to raw");
+                BodyFormat::Raw
+            }
+        }
+    }
+}
+
+/// Register the user-defined blocks of the templates, such as `blocks/<name>.jinja`.
+///
+/// The builtin blocks can't be redefined, the `templates` should exclude the
+/// builtin templates, which are overridden rather than defining new blocks.
+pub fn register_template_blocks<'a>(templates: impl Iterator<Item = (&'a String, &'a String)>) {
+    let blocks = templates
+        .filter_map(|(name, template)| {
+            let block = name.strip_prefix("blocks/")?.strip_suffix(".jinja")?;
+            if block.contains('/') || ALL_CODE_BLOCKS.contains(&block) {
+                return None;
+            }
+            Some((block.to_owned(), BodyFormat::from_template(template)))
+        })
+        .collect();
+    *TEMPLATE_BLOCKS.write() = blocks;
+}
+
+pub fn is_template_block(name: &str) -> bool {
+    TEMPLATE_BLOCKS.read().contains_key(name)
+}
+
+/// The TemplateBlock to render a user-defined block with its template.
+///
+/// The template context:
+/// - `name`, the block name.
+/// - `options`, the fenced options.
+/// - `body`, the block body in the declared format.
+pub struct TemplateBlock<'a> {
+    name: &'a str,
+    options: &'a HashMap<String, &'a str>,
+    body: &'a str,
+}
+
+impl<'a> TemplateBlock<'a> {
+    pub fn new(name: &'a str, options: &'a HashMap<String, &'a str>, body: &'a str) -> Self {
+        TemplateBlock {
+            name,
+            options,
+            body,
+        }
+    }
+}
+
+impl<'a> CodeBlock for TemplateBlock<'a> {
+    fn render(&self) -> Result<String> {
+        let format = TEMPLATE_BLOCKS
+            .read()
+            .get(self.name)
+            .copied()
+            .unwrap_or_default();
+
+        let mut context = Context::new();
+        context.insert("name", self.name);
+        context.insert("options", self.options);
+        match format {
+            BodyFormat::Raw => context.insert("body", self.body),
+            BodyFormat::Toml => {
+                let body = toml::from_str::<toml::Value>(self.body)
+                    .with_context(|| format!("Invalid toml body of `{}` block", self.name))?;
+                context.insert("body", &body);
+            }
+            BodyFormat::Markdown => {
+                let zine_data = data::read();
+                let markdown_config = zine_data.get_markdown_config();
+                let html = MarkdownRender::new(markdown_config).render_html(self.body);
+                context.insert("body", &html);
+            }
+        }
+        let html = engine::get_tera().render(&format!("blocks/{}.jinja", self.name), &context)?;
+        Ok(html)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::BodyFormat;
+
+    #[test_case("<div>{{ body }}</div>", BodyFormat::Raw; "no declaration")]
+    #[test_case("{# body: toml #}\n<div></div>", BodyFormat::Toml; "toml")]
+    #[test_case("\n{#body:markdown#}<div></div>", BodyFormat::Markdown; "markdown")]
+    #[test_case("{# A comment #}", BodyFormat::Raw; "comment")]
+    #[test_case("{# body: yaml #}", BodyFormat::Raw; "unknown")]
+    fn test_body_format(template: &str, expected: BodyFormat) {
+        assert_eq!(BodyFormat::from_template(template), expected);
+    }
+}
@@ -1,11 +1,25 @@
 use std::{collections::HashMap, fmt::Write};
 
 use anyhow::Result;
+use pulldown_cmark::escape::{escape_href, escape_html};
+use tera::Context;
+use url::Url;
 
-use crate::data::UrlPreviewInfo;
+use crate::{data::UrlPreviewInfo, engine};
 
 use super::CodeBlock;
 
+/// The UrlPreviewBlock to render the preview card of the url.
+///
+/// The fetched title, description and image can be overridden in the options:
+///
+/// ````markdown
+/// ```urlpreview, title: Zine, image: https://zineland.io/cover.png
+/// https://zineland.io
+/// ```
+/// ````
+///
+/// The `image: false` option hides the preview image.
 pub(super) struct UrlPreviewBlock<'a> {
     url: &'a str,
     info: UrlPreviewInfo,
@@ -17,28 +31,83 @@ impl<'a> UrlPreviewBlock<'a> {
     pub(super) fn new(
         options: HashMap<String, &'a str>,
         url: &'a str,
-        info: UrlPreviewInfo,
+        mut info: UrlPreviewInfo,
     ) -> Self {
+        if let Some(title) = options.get("title") {
+            info.title = title.to_string();
+        }
+        if let Some(description) = options.get("description") {
+            info.description = description.to_string();
+        }
+        let show_image = match options.get("image") {
+            Some(image) => match str::parse::<bool>(image) {
+                Ok(show_image) => show_image,
+                Err(_) => {
+                    info.image = Some(image.to_string());
+                    info.local_image = None;
+                    true
+                }
+            },
+            None => true,
+        };
         UrlPreviewBlock {
             url,
             info,
-            show_image: options
-                .get("image")
-                .and_then(|v| str::parse::<bool>(v).ok())
-                .unwrap_or(true),
+            show_image,
+        }
+    }
+
+    /// The preview info of the plain link card, whose title is the host of the url.
+    pub(super) fn link_info(url: &str) -> UrlPreviewInfo {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_owned));
+        UrlPreviewInfo {
+            title: host.unwrap_or_else(|| url.to_owned()),
+            ..Default::default()
         }
     }
+
+    /// Whether the title is overridden, then the block can be rendered without the fetched preview.
+    pub(super) fn has_override(options: &HashMap<String, &'a str>) -> bool {
+        options.contains_key("title")
+    }
 }
 
 impl<'a> CodeBlock for UrlPreviewBlock<'a> {
     fn render(&self) -> Result<String> {
         let mut html = String::new();
         writeln!(&mut html, r#"<div class="url-preview">"#)?;
-        writeln!(&mut html, r#" <div>{}</div>"#, self.info.title)?;
-        writeln!(&mut html, r#" <div>{}</div>"#, self.info.description)?;
+        write!(&mut html, r#" <div>"#)?;
+        escape_html(&mut html, &self.info.title)?;
+        writeln!(&mut html, r#"</div>"#)?;
+        write!(&mut html, r#" <div>"#)?;
+        escape_html(&mut html, &self.info.description)?;
+        writeln!(&mut html, r#"</div>"#)?;
+        if self.info.site_name.is_some() || self.info.favicon.is_some() {
+            write!(&mut html, r#" <div class="url-preview-site">"#)?;
+            if let Some(favicon) = self.info.favicon.as_ref() {
+                html.push_str(r#"<img class="url-preview-favicon" src=""#);
+                escape_href(&mut html, favicon)?;
+                html.push_str(r#"" alt="" loading="lazy">"#);
+            }
+            if let Some(site_name) = self.info.site_name.as_ref() {
+                html.push_str("<span>");
+                escape_html(&mut html, site_name)?;
+                html.push_str("</span>");
+            }
+            writeln!(&mut html, r#"</div>"#)?;
+        }
         writeln!(&mut html, r#" <a href="{url}">{url}</a>"#, url = self.url)?;
         if self.show_image {
-            if let Some(image) = self.info.image.as_ref().filter(|i| !i.is_empty()) {
+            if let Some(local_image) = self.info.local_image.as_ref() {
+                // The downloaded image is resized by the image pipeline.
+                let mut context = Context::new();
+                context.insert("src", local_image);
+                context.insert("alt", "");
+                context.insert("title", "");
+                html.push_str(&engine::get_tera().render("image.jinja", &context)?);
+            } else if let Some(image) = self.info.image.as_ref().filter(|i| !i.is_empty()) {
                 writeln!(&mut html, r#" <img src="{}" />"#, image)?;
             }
         }
@@ -60,3 +129,37 @@ impl<'a> CodeBlock for UrlPreviewError<'a> {
         Ok(html)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{code_blocks::Fenced, data::UrlPreviewInfo};
+
+    use super::UrlPreviewBlock;
+
+    #[test]
+    fn test_override_preview() {
+        let info = UrlPreviewInfo {
+            title: "Fetched".to_owned(),
+            description: "Description".to_owned(),
+            image: Some("https://example.com/a.png".to_owned()),
+            local_image: Some("/static/previews/0123456789abcdef.png".to_owned()),
+            ..Default::default()
+        };
+        let fenced =
+            Fenced::parse("urlpreview, title: Zine, image: https://zineland.io/b.png").unwrap();
+        let block = UrlPreviewBlock::new(fenced.options, "https://zineland.io", info.clone());
+        assert_eq!(block.info.title, "Zine");
+        assert_eq!(block.info.description, "Description");
+        assert_eq!(
+            block.info.image.as_deref(),
+            Some("https://zineland.io/b.png")
+        );
+        assert_eq!(block.info.local_image, None);
+        assert!(block.show_image);
+
+        let fenced = Fenced::parse("urlpreview, image: false").unwrap();
+        let block = UrlPreviewBlock::new(fenced.options, "https://zineland.io", info);
+        assert_eq!(block.info.title, "Fetched");
+        assert!(!block.show_image);
+    }
+}
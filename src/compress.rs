@@ -0,0 +1,124 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::Result;
+use flate2::{write::GzEncoder, Compression};
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use walkdir::WalkDir;
+
+/// The extensions of the compressible files.
+static COMPRESSIBLE_EXTENSIONS: &[&str] = &["html", "xml", "css", "js", "json", "svg"];
+
+/// Write the precompressed `.gz` and `.br` siblings of all compressible files in `dest`.
+///
+/// The sibling is skipped if it isn't smaller than the original file.
+pub fn compress_dir(dest: &Path) -> Result<()> {
+    let compressed = AtomicUsize::new(0);
+    WalkDir::new(dest)
+        .into_iter()
+        .par_bridge()
+        .try_for_each(|entry| {
+            let entry = entry?;
+            let path = entry.path();
+            let is_compressible = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| COMPRESSIBLE_EXTENSIONS.contains(&ext));
+            if !path.is_file() || !is_compressible {
+                return anyhow::Ok(());
+            }
+
+            let content = fs::read(path)?;
+            write_sibling(path, "gz", &gzip(&content)?, content.len())?;
+            write_sibling(path, "br", &brotli(&content)?, content.len())?;
+            compressed.fetch_add(1, Ordering::Relaxed);
+            anyhow::Ok(())
+        })?;
+
+    println!("Compressed {} files.", compressed.into_inner());
+    Ok(())
+}
+
+fn gzip(content: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(content)?;
+    Ok(encoder.finish()?)
+}
+
+fn brotli(content: &[u8]) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    {
+        let mut writer = brotli::CompressorWriter::new(&mut output, 4096, 11, 22);
+        writer.write_all(content)?;
+    }
+    Ok(output)
+}
+
+// Write `compressed` to the sibling of `path` with the extra `extension`,
+// such as `index.html.gz`.
+fn write_sibling(
+    path: &Path,
+    extension: &str,
+    compressed: &[u8],
+    original_len: usize,
+) -> Result<()> {
+    let mut sibling = PathBuf::from(path).into_os_string();
+    sibling.push(".");
+    sibling.push(extension);
+
+    if compressed.len() >= original_len {
+        // Remove the stale sibling of the previous build.
+        let _ = fs::remove_file(&sibling);
+        return Ok(());
+    }
+
+    let mut writer = BufWriter::new(File::create(&sibling)?);
+    writer.write_all(compressed)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Read};
+
+    use flate2::read::GzDecoder;
+
+    use super::compress_dir;
+
+    #[test]
+    fn test_compress_dir() {
+        let dest = tempfile::tempdir().unwrap();
+        let dest = dest.path();
+        fs::create_dir_all(dest.join("static")).unwrap();
+        let html = "<p>Hello, zine!</p>".repeat(100);
+        fs::write(dest.join("index.html"), &html).unwrap();
+        fs::write(dest.join("static/tiny.css"), "a{}").unwrap();
+        fs::write(dest.join("static/cover.png"), &html).unwrap();
+
+        compress_dir(dest).unwrap();
+
+        let mut decompressed = String::new();
+        GzDecoder::new(fs::File::open(dest.join("index.html.gz")).unwrap())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, html);
+
+        let mut decompressed = String::new();
+        brotli::Decompressor::new(fs::File::open(dest.join("index.html.br")).unwrap(), 4096)
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, html);
+
+        // Not smaller than the original file.
+        assert!(!dest.join("static/tiny.css.gz").exists());
+        // Not compressible.
+        assert!(!dest.join("static/cover.png.gz").exists());
+        // Never compress the compressed siblings again.
+        assert!(!dest.join("index.html.gz.gz").exists());
+    }
+}
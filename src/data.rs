@@ -1,9 +1,11 @@
 use std::{
+    borrow::Cow,
     collections::BTreeMap,
     fs::{self, File},
     io::Write,
     path::Path,
     sync::Arc,
+    time::Duration,
 };
 
 use anyhow::Result;
@@ -15,11 +17,13 @@ use serde::{
     ser::{SerializeMap, SerializeSeq},
     Deserialize, Serialize,
 };
+use time::OffsetDateTime;
 use tokio::sync::watch::{self, Receiver};
+use url::Url;
 
 use crate::{
     entity::{Author, MarkdownConfig, MetaArticle, Site, Theme},
-    helpers, html,
+    html, http, images,
 };
 
 static ZINE_DATA: OnceCell<RwLock<ZineData>> = OnceCell::new();
@@ -47,11 +51,70 @@ pub fn export<P: AsRef<Path>>(path: P) -> Result<()> {
     Ok(())
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct UrlPreviewInfo {
     pub title: String,
     pub description: String,
     pub image: Option<String>,
+    // The root path url of the downloaded image, such as `/static/previews/{hash}.png`.
+    pub local_image: Option<String>,
+    pub site_name: Option<String>,
+    pub favicon: Option<String>,
+    // The unix timestamp when the preview was fetched,
+    // `None` if the preview was cached by the old version.
+    pub fetched_at: Option<i64>,
+}
+
+impl UrlPreviewInfo {
+    /// Fetch the url and parse the preview info from its HTML meta.
+    pub async fn fetch(url: &str) -> Result<Self> {
+        let page = http::fetch_html(url).await?;
+        let meta = html::parse_html_meta(page.html.as_bytes());
+        // Resolve the relative image and favicon url with the final page url.
+        let base_url = Url::parse(&page.url).ok();
+        let resolve = |link: Cow<str>| match base_url.as_ref().map(|base| base.join(&link)) {
+            Some(Ok(link)) => link.to_string(),
+            _ => link.into_owned(),
+        };
+        Ok(UrlPreviewInfo {
+            title: meta.title.into_owned(),
+            description: meta.description.into_owned(),
+            image: meta.image.map(resolve),
+            local_image: None,
+            site_name: meta.site_name.map(Cow::into_owned),
+            favicon: meta.favicon.map(resolve),
+            fetched_at: Some(OffsetDateTime::now_utc().unix_timestamp()),
+        })
+    }
+
+    /// Whether the preview was fetched `max_age` ago or fetched at unknown time.
+    pub fn is_older_than(&self, max_age: Duration) -> bool {
+        match self.fetched_at {
+            Some(fetched_at) => {
+                OffsetDateTime::now_utc().unix_timestamp() - fetched_at >= max_age.as_secs() as i64
+            }
+            None => true,
+        }
+    }
+
+    /// Whether the image should be downloaded, such as the thumbnails of the embeds.
+    pub fn needs_image_download(&self, download_image: bool) -> bool {
+        download_image
+            && self.image.as_ref().is_some_and(|image| !image.is_empty())
+            && !self
+                .local_image
+                .as_deref()
+                .is_some_and(images::has_preview_image)
+    }
+
+    /// Download the image into the `static/previews` directory.
+    pub async fn download_image(&mut self) -> Result<()> {
+        if let Some(image) = self.image.as_deref().filter(|image| !image.is_empty()) {
+            let bytes = http::fetch_bytes(image).await?;
+            self.local_image = Some(images::save_preview_image(&bytes)?);
+        }
+        Ok(())
+    }
 }
 
 impl Serialize for UrlPreviewInfo {
@@ -59,14 +122,18 @@ impl Serialize for UrlPreviewInfo {
     where
         S: serde::Serializer,
     {
-        let mut seq = serializer.serialize_seq(Some(3))?;
+        let mut seq = serializer.serialize_seq(Some(7))?;
         seq.serialize_element(&self.title)?;
         seq.serialize_element(&self.description)?;
-        if let Some(image) = self.image.as_ref() {
-            seq.serialize_element(image)?;
-        } else {
-            seq.serialize_element("")?;
+        for value in [
+            &self.image,
+            &self.local_image,
+            &self.site_name,
+            &self.favicon,
+        ] {
+            seq.serialize_element(value.as_deref().unwrap_or_default())?;
         }
+        seq.serialize_element(&self.fetched_at)?;
         seq.end()
     }
 }
@@ -86,22 +153,33 @@ impl<'de> de::Visitor<'de> for UrlPreviewInfoVisitor {
     type Value = UrlPreviewInfo;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("2 or 3 elements tuple")
+        formatter.write_str("2 to 7 elements tuple")
     }
 
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
     where
         A: de::SeqAccess<'de>,
     {
-        let (title, description, image) = (
+        let (title, description, image, local_image) = (
             seq.next_element()?.unwrap_or_default(),
             seq.next_element()?.unwrap_or_default(),
             seq.next_element()?,
+            seq.next_element::<String>()?.filter(|v| !v.is_empty()),
+        );
+        // The fields added later are empty in the old data.
+        let (site_name, favicon, fetched_at) = (
+            seq.next_element::<String>()?.filter(|v| !v.is_empty()),
+            seq.next_element::<String>()?.filter(|v| !v.is_empty()),
+            seq.next_element::<Option<i64>>()?.flatten(),
         );
         Ok(UrlPreviewInfo {
             title,
             description,
             image,
+            local_image,
+            site_name,
+            favicon,
+            fetched_at,
         })
     }
 }
@@ -126,6 +204,9 @@ pub struct ZineData {
     // The preview tasks.
     #[serde(skip)]
     preview_tasks: DashMap<String, Receiver<Option<PreviewEvent>>>,
+    // Whether never fetch the url previews.
+    #[serde(skip)]
+    offline: bool,
     // All url preview data.
     url_previews: Arc<DashMap<String, UrlPreviewInfo>>,
 }
@@ -170,6 +251,7 @@ impl ZineData {
                 theme: Theme::default(),
                 url_previews: Arc::new(DashMap::default()),
                 preview_tasks: DashMap::default(),
+                offline: false,
             })
         }
     }
@@ -182,10 +264,25 @@ impl ZineData {
         self.url_previews.get(url).map(|u| u.to_owned())
     }
 
+    pub fn set_offline(&mut self, offline: bool) -> &mut Self {
+        self.offline = offline;
+        self
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
     /// Preview url asynchronously, return a tuple.
     /// The first bool argument indicating whether is a first time previewing.
     /// The second argument is the receiver to wait preview event finished.
-    pub fn preview_url(&self, url: &str) -> (bool, Receiver<Option<PreviewEvent>>) {
+    ///
+    /// The image is downloaded if `download_image` is true.
+    pub fn preview_url(
+        &self,
+        url: &str,
+        download_image: bool,
+    ) -> (bool, Receiver<Option<PreviewEvent>>) {
         if let Some(rx) = self.preview_tasks.get(url) {
             // In the preview queue.
             (false, rx.clone())
@@ -195,18 +292,23 @@ impl ZineData {
             self.preview_tasks.insert(url.to_owned(), rx.clone());
 
             let url = url.to_owned();
+            let cached = self.get_preview(&url);
             let list = Arc::clone(&self.url_previews);
-            // Spawn a background task to preview the url.
+            // Spawn a background task to preview the url,
+            // or only download the image of the cached preview.
             tokio::spawn(async move {
-                match helpers::fetch_url(&url).await {
-                    Ok(html) => {
-                        let meta = html::parse_html_meta(html);
-                        let info = UrlPreviewInfo {
-                            title: meta.title.into_owned(),
-                            description: meta.description.into_owned(),
-                            image: meta.image.as_ref().map(|image| image.to_string()),
-                        };
-
+                let info = match cached {
+                    Some(info) => Ok(info),
+                    None => UrlPreviewInfo::fetch(&url).await,
+                };
+                match info {
+                    Ok(mut info) => {
+                        if info.needs_image_download(download_image) {
+                            // The preview is still usable if the downloading failed.
+                            if let Err(err) = info.download_image().await {
+                                println!("Warning: download the image of `{url}` failed: {err}");
+                            }
+                        }
                         list.insert(url, info.clone());
                         tx.send(Some(PreviewEvent::Finished(info)))
                     }
@@ -1,28 +1,72 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
 };
 
 use crate::{
-    current_mode, data,
-    entity::{Entity, Zine},
+    code_blocks, current_mode, data,
+    entity::{Entity, OfflinePreviews, Zine},
+    fingerprint,
     helpers::copy_dir,
     html::rewrite_html_base_url,
+    http, images,
     locales::FluentLoader,
-    markdown::MarkdownRender,
-    Mode,
+    markdown::{self, MarkdownRender},
+    minify, preview, Mode,
 };
 
-use anyhow::Result;
+use anyhow::{bail, Context as _, Result};
 use hyper::Uri;
 use once_cell::sync::OnceCell;
 use serde_json::Value;
 use tera::{Context, Tera};
+use walkdir::WalkDir;
 
 static TERA: OnceCell<parking_lot::RwLock<Tera>> = OnceCell::new();
+/// The stylesheet (relative to the dest directory) of the classed highlighted code.
+static HIGHLIGHT_STYLESHEET: &str = "static/zine-highlight.css";
+
+// The builtin templates, which are embedded in release version.
+fn builtin_templates() -> Vec<(&'static str, &'static str)> {
+    vec![
+        (
+            "_article_ref.jinja",
+            include_str!("../templates/_article_ref.jinja"),
+        ),
+        ("_macros.jinja", include_str!("../templates/_macros.jinja")),
+        ("_meta.jinja", include_str!("../templates/_meta.jinja")),
+        ("heading.jinja", include_str!("../templates/heading.jinja")),
+        ("image.jinja", include_str!("../templates/image.jinja")),
+        ("base.jinja", include_str!("../templates/base.jinja")),
+        ("index.jinja", include_str!("../templates/index.jinja")),
+        ("issue.jinja", include_str!("../templates/issue.jinja")),
+        ("article.jinja", include_str!("../templates/article.jinja")),
+        ("author.jinja", include_str!("../templates/author.jinja")),
+        (
+            "author-list.jinja",
+            include_str!("../templates/author-list.jinja"),
+        ),
+        ("topic.jinja", include_str!("../templates/topic.jinja")),
+        (
+            "topic-list.jinja",
+            include_str!("../templates/topic-list.jinja"),
+        ),
+        ("page.jinja", include_str!("../templates/page.jinja")),
+        ("feed.jinja", include_str!("../templates/feed.jinja")),
+        ("sitemap.jinja", include_str!("../templates/sitemap.jinja")),
+        (
+            "blocks/embed.jinja",
+            include_str!("../templates/blocks/embed.jinja"),
+        ),
+        (
+            "blocks/quote.jinja",
+            include_str!("../templates/blocks/quote.jinja"),
+        ),
+    ]
+}
 
-fn init_tera(source: &Path, zine: &Zine) {
+fn init_tera(source: &Path, zine: &Zine) -> Result<()> {
     TERA.get_or_init(|| {
         // Debug version tera which need to reload templates.
         #[cfg(debug_assertions)]
@@ -32,39 +76,11 @@ fn init_tera(source: &Path, zine: &Zine) {
         #[cfg(not(debug_assertions))]
         let mut tera = Tera::default();
         #[cfg(not(debug_assertions))]
-        tera.add_raw_templates(vec![
-            (
-                "_article_ref.jinja",
-                include_str!("../templates/_article_ref.jinja"),
-            ),
-            ("_macros.jinja", include_str!("../templates/_macros.jinja")),
-            ("_meta.jinja", include_str!("../templates/_meta.jinja")),
-            ("heading.jinja", include_str!("../templates/heading.jinja")),
-            ("base.jinja", include_str!("../templates/base.jinja")),
-            ("index.jinja", include_str!("../templates/index.jinja")),
-            ("issue.jinja", include_str!("../templates/issue.jinja")),
-            ("article.jinja", include_str!("../templates/article.jinja")),
-            ("author.jinja", include_str!("../templates/author.jinja")),
-            (
-                "author-list.jinja",
-                include_str!("../templates/author-list.jinja"),
-            ),
-            ("topic.jinja", include_str!("../templates/topic.jinja")),
-            (
-                "topic-list.jinja",
-                include_str!("../templates/topic-list.jinja"),
-            ),
-            ("page.jinja", include_str!("../templates/page.jinja")),
-            ("feed.jinja", include_str!("../templates/feed.jinja")),
-            ("sitemap.jinja", include_str!("../templates/sitemap.jinja")),
-            (
-                "blocks/quote.jinja",
-                include_str!("../templates/blocks/quote.jinja"),
-            ),
-        ])
-        .unwrap();
+        tera.add_raw_templates(builtin_templates()).unwrap();
         tera.register_function("markdown_to_html", markdown_to_html_fn);
         tera.register_function("get_author", get_author_fn);
+        tera.register_function("get_image", get_image_fn);
+        tera.register_function("asset_url", asset_url_fn);
 
         parking_lot::RwLock::new(tera)
     });
@@ -75,6 +91,57 @@ fn init_tera(source: &Path, zine: &Zine) {
     // Notice: the full reloading should take place before adding dynamic templates.
     #[cfg(debug_assertions)]
     tera.full_reload().expect("reload tera template failed");
+    // Restore the builtin templates, which may be overridden in the previous build.
+    #[cfg(not(debug_assertions))]
+    tera.add_raw_templates(builtin_templates())
+        .expect("reload tera template failed");
+
+    // Override the builtin templates with the theme package templates,
+    // then the project templates of the same name.
+    let templates_dirs = zine
+        .theme
+        .package
+        .iter()
+        .map(|package| package.dir().join(crate::ZINE_TEMPLATES_DIR))
+        .chain([source.join(crate::ZINE_TEMPLATES_DIR)]);
+    let mut templates = HashMap::new();
+    for templates_dir in templates_dirs.filter(|dir| dir.is_dir()) {
+        for entry in WalkDir::new(&templates_dir) {
+            let entry = entry?;
+            let path = entry.path();
+            let is_template = path.is_file() && path.extension().is_some_and(|ext| ext == "jinja");
+            if !is_template {
+                continue;
+            }
+
+            // Use `/` as separator on every platform, such as `blocks/quote.jinja`.
+            let name = path
+                .strip_prefix(&templates_dir)?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let template = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read template `{}`", path.display()))?;
+            templates.insert(name, template);
+        }
+    }
+    // The new templates of `blocks/` define the user-defined code blocks.
+    // Compare with the builtin templates, the project templates added
+    // in the previous build are still in the tera.
+    let builtin_names = builtin_templates()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect::<HashSet<_>>();
+    code_blocks::register_template_blocks(
+        templates
+            .iter()
+            .filter(|(name, _)| !builtin_names.contains(name.as_str())),
+    );
+    if !templates.is_empty() {
+        tera.add_raw_templates(templates)
+            .context("Failed to add the custom templates")?;
+    }
 
     // Dynamically add templates.
     if let Some(head_template) = zine.theme.head_template.as_ref() {
@@ -91,7 +158,12 @@ fn init_tera(source: &Path, zine: &Zine) {
     }
 
     // Dynamically register functions that need dynamic configuration.
-    tera.register_function("fluent", FluentLoader::new(source, &zine.site.locale));
+    let theme_dir = zine.theme.package.as_ref().map(|package| package.dir());
+    tera.register_function(
+        "fluent",
+        FluentLoader::new(source, theme_dir, &zine.site.locale),
+    );
+    Ok(())
 }
 
 /// Get a Tera under read lock.
@@ -99,6 +171,12 @@ pub fn get_tera() -> parking_lot::RwLockReadGuard<'static, Tera> {
     TERA.get().expect("Tera haven't initialized").read()
 }
 
+/// Whether the template exists, such as the user-defined `shortcodes/kbd.jinja`.
+pub fn has_template(name: &str) -> bool {
+    TERA.get()
+        .is_some_and(|tera| tera.read().get_template_names().any(|n| n == name))
+}
+
 #[derive(Debug)]
 pub struct ZineEngine {
     pub source: PathBuf,
@@ -116,6 +194,8 @@ pub fn render(template: &str, context: &Context, dest: impl AsRef<Path>) -> Resu
     }
 
     get_tera().render_to(template, context, &mut buf)?;
+    let mermaid_script = data::read().get_markdown_config().mermaid_script.clone();
+    code_blocks::inject_mermaid_script(&mut buf, &mermaid_script);
 
     // Rewrite some site url and cdn links if and only if:
     // 1. in build run mode
@@ -136,7 +216,11 @@ pub fn render(template: &str, context: &Context, dest: impl AsRef<Path>) -> Resu
             }
         }
 
-        let html = rewrite_html_base_url(&buf, site_url, cdn_url)?;
+        let manifest = fingerprint::manifest();
+        let mut html = rewrite_html_base_url(&buf, site_url, cdn_url, Some(&manifest))?;
+        if minify::is_enabled() {
+            html = minify::minify_html(&html);
+        }
         fs::write(dest, html)?;
         return Ok(());
     }
@@ -155,26 +239,18 @@ pub fn render_str(raw_template: &str, context: &Context) -> Result<String> {
 // Render Atom feed
 fn render_atom_feed(context: Context, dest: impl AsRef<Path>) -> Result<()> {
     let dest = dest.as_ref().join("feed.xml");
-    tokio::task::spawn_blocking(move || {
-        let mut buf = vec![];
-        get_tera()
-            .render_to("feed.jinja", &context, &mut buf)
-            .expect("Render feed.jinja failed.");
-        fs::write(dest, buf).expect("Write feed.xml failed");
-    });
+    let mut buf = vec![];
+    get_tera().render_to("feed.jinja", &context, &mut buf)?;
+    fs::write(dest, buf)?;
     Ok(())
 }
 
 // Render sitemap.xml
 fn render_sitemap(context: Context, dest: impl AsRef<Path>) -> Result<()> {
     let dest = dest.as_ref().join("sitemap.xml");
-    tokio::task::spawn_blocking(move || {
-        let mut buf = vec![];
-        get_tera()
-            .render_to("sitemap.jinja", &context, &mut buf)
-            .expect("Render sitemap.jinja failed.");
-        fs::write(dest, buf).expect("Write sitemap.xml failed");
-    });
+    let mut buf = vec![];
+    get_tera().render_to("sitemap.jinja", &context, &mut buf)?;
+    fs::write(dest, buf)?;
     Ok(())
 }
 
@@ -192,11 +268,6 @@ impl ZineEngine {
     }
 
     fn copy_static_assets(&self) -> Result<()> {
-        let static_dir = self.source.join("static");
-        if static_dir.exists() {
-            copy_dir(&static_dir, &self.dest)?;
-        }
-
         // Copy builtin static files into dest static dir.
         let dest_static_dir = self.dest.join("static");
         fs::create_dir_all(&dest_static_dir)?;
@@ -207,18 +278,77 @@ impl ZineEngine {
         #[cfg(debug_assertions)]
         copy_dir(Path::new("./static"), &self.dest)?;
 
+        // The static files of theme package, then the project's own static files
+        // override the builtin static files of the same name.
+        let static_dirs = self
+            .zine
+            .theme
+            .package
+            .iter()
+            .map(|package| package.dir().join("static"))
+            .chain([self.source.join("static")]);
+        for static_dir in static_dirs.filter(|dir| dir.exists()) {
+            copy_dir(&static_dir, &self.dest)?;
+        }
+
         Ok(())
     }
 
+    // Report the url previews have no cache in offline mode.
+    fn check_uncached_previews(&self) -> Result<()> {
+        let urls = preview::uncached_preview_urls(&self.source)?;
+        if urls.is_empty() {
+            return Ok(());
+        }
+        let list = urls.iter().fold(String::new(), |mut list, url| {
+            list.push_str("\n- ");
+            list.push_str(url);
+            list
+        });
+        match self.zine.build_config.offline_previews {
+            OfflinePreviews::Link => {
+                println!("Warning: the following url previews are uncached, rendered as plain links:{list}");
+                Ok(())
+            }
+            OfflinePreviews::Error => bail!(
+                "The following url previews are uncached in offline mode, run `zine preview fetch` to cache them:{list}"
+            ),
+        }
+    }
+
     pub fn build(&mut self, reload: bool) -> Result<()> {
         if reload {
             self.zine = Zine::parse_from_toml(&self.source)?;
         }
 
+        markdown::load_highlight_assets(&self.source, &self.zine.markdown_config)?;
         self.zine.parse(&self.source)?;
 
-        init_tera(&self.source, &self.zine);
+        init_tera(&self.source, &self.zine)?;
+        images::init(&self.source, &self.dest, &self.zine.image_config);
+        http::init(&self.zine.http_config)?;
+
+        // Static assets must be ready before rendering, the rendered pages
+        // reference the fingerprinted assets.
+        self.copy_static_assets()?;
+        if let Some(stylesheet) = markdown::highlight_stylesheet(&self.zine.markdown_config)? {
+            fs::write(self.dest.join(HIGHLIGHT_STYLESHEET), stylesheet)?;
+        }
+        let is_build_mode = matches!(current_mode(), Mode::Build);
+        minify::init(self.zine.build_config.minify && is_build_mode);
+        if minify::is_enabled() {
+            // Minify before fingerprinting, the hash should be the minified content's.
+            minify::minify_static_assets(&self.dest)?;
+        }
+        if self.zine.build_config.fingerprint && is_build_mode {
+            fingerprint::fingerprint_static_assets(&self.dest)?;
+        } else {
+            fingerprint::clear();
+        }
 
+        if data::read().is_offline() {
+            self.check_uncached_previews()?;
+        }
         self.zine.render(Context::new(), &self.dest)?;
         #[cfg(debug_assertions)]
         println!("Zine engine: {:?}", self.zine);
@@ -234,7 +364,8 @@ impl ZineEngine {
         sitemap_context.insert("entries", &self.zine.sitemap_entries());
         render_sitemap(sitemap_context, &self.dest)?;
 
-        self.copy_static_assets()
+        minify::print_report();
+        Ok(())
     }
 }
 
@@ -259,3 +390,68 @@ fn get_author_fn(map: &HashMap<String, Value>) -> tera::Result<Value> {
         Ok(Value::Null)
     }
 }
+
+// A tera function to get the processed responsive image of a local image url.
+fn get_image_fn(map: &HashMap<String, Value>) -> tera::Result<Value> {
+    if let Some(Value::String(src)) = map.get("src") {
+        Ok(serde_json::to_value(images::process(src))?)
+    } else {
+        Ok(Value::Null)
+    }
+}
+
+// A tera function to get the fingerprinted url of a static asset,
+// fallback to the original url if it isn't fingerprinted.
+fn asset_url_fn(map: &HashMap<String, Value>) -> tera::Result<Value> {
+    if let Some(Value::String(path)) = map.get("path") {
+        let manifest = fingerprint::manifest();
+        let url = manifest.get(path).unwrap_or(path);
+        Ok(Value::String(url.to_owned()))
+    } else {
+        Ok(Value::Null)
+    }
+}
+
+/// Initialize the tera of an empty project in a temporary directory for testing.
+#[cfg(test)]
+pub(crate) fn init_test_tera() -> tempfile::TempDir {
+    let source = tempfile::tempdir().unwrap();
+    fs::write(
+        source.path().join(crate::ZINE_FILE),
+        "[site]\nurl = \"http://localhost\"\nname = \"Test\"\n",
+    )
+    .unwrap();
+    init_tera(
+        source.path(),
+        &Zine::parse_from_toml(source.path()).unwrap(),
+    )
+    .unwrap();
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{init_tera, init_test_tera};
+    use crate::{code_blocks, entity::Zine};
+
+    #[test]
+    fn test_init_tera_twice() {
+        let source = init_test_tera();
+        let source = source.path();
+        let blocks_dir = source.join(crate::ZINE_TEMPLATES_DIR).join("blocks");
+        fs::create_dir_all(&blocks_dir).unwrap();
+        fs::write(blocks_dir.join("greeting.jinja"), "Hello {{ body }}").unwrap();
+        // Override a builtin block, which isn't a user-defined block.
+        fs::write(blocks_dir.join("quote.jinja"), "{{ quote.content }}").unwrap();
+
+        let zine = Zine::parse_from_toml(source).unwrap();
+        // Such as the rebuilds of `zine serve`.
+        for _ in 0..2 {
+            init_tera(source, &zine).unwrap();
+            assert!(code_blocks::is_template_block("greeting"));
+            assert!(!code_blocks::is_template_block("quote"));
+        }
+    }
+}
@@ -1,4 +1,9 @@
-use std::{borrow::Cow, collections::HashMap, fs, path::Path};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{ensure, Context as _, Result};
 use serde::{Deserialize, Serialize};
@@ -9,7 +14,7 @@ use crate::{
     current_mode, data, engine,
     html::Meta,
     i18n,
-    markdown::{self, MarkdownRender},
+    markdown::{self, ColocatedAssets, MarkdownRender},
     Mode,
 };
 
@@ -44,6 +49,9 @@ pub struct Article {
     /// The article's markdown content.
     #[serde(default, skip_serializing)]
     pub markdown: String,
+    /// The directory of the markdown file, where the co-located assets located.
+    #[serde(skip)]
+    dir: PathBuf,
     /// The optional topics of this article.
     #[serde(default)]
     #[serde(rename(deserialize = "topic"))]
@@ -147,6 +155,10 @@ impl Article {
         self.markdown = fs::read_to_string(&file_path).with_context(|| {
             format!("Failed to read markdown file of `{}`", file_path.display())
         })?;
+        self.dir = file_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
 
         // Fallback to file name if no slug specified.
         if self.meta.path.is_none() && self.meta.slug.is_empty() {
@@ -166,30 +178,37 @@ impl Article {
     }
 
     fn render(&self, mut context: Context, dest: &Path) -> Result<()> {
+        // The url (without the prefix slash) and the output directory of this article.
+        let (url, dest) = if let Some(path) = self
+            .meta
+            .path
+            .as_ref()
+            // Remove the prefix slash
+            .and_then(|path| path.strip_prefix('/'))
+        {
+            let mut dest = dest.to_path_buf();
+            dest.pop();
+            (Cow::Borrowed(path), dest.join(path))
+        } else {
+            let issue_slug = context
+                .get("issue")
+                .and_then(|issue| issue.get("slug"))
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            (
+                Cow::Owned(format!("{}/{}", issue_slug, self.meta.slug)),
+                dest.join(&self.meta.slug),
+            )
+        };
+
         context.insert(
             "meta",
             &Meta {
                 title: Cow::Borrowed(&self.meta.title),
                 description: Cow::Owned(markdown::extract_description(&self.markdown)),
-                url: Some(
-                    if let Some(path) = self
-                        .meta
-                        .path
-                        .as_ref()
-                        // Remove the prefix slash
-                        .and_then(|path| path.strip_prefix('/'))
-                    {
-                        Cow::Borrowed(path)
-                    } else {
-                        let issue_slug = context
-                            .get("issue")
-                            .and_then(|issue| issue.get("slug"))
-                            .and_then(|v| v.as_str())
-                            .unwrap_or_default();
-                        Cow::Owned(format!("{}/{}", issue_slug, self.meta.slug))
-                    },
-                ),
+                url: Some(url.clone()),
                 image: self.meta.cover.as_deref().map(Cow::Borrowed),
+                ..Default::default()
             },
         );
         context.insert("page_type", "article");
@@ -199,23 +218,18 @@ impl Article {
         let zine_data = data::read();
         let markdown_config = zine_data.get_markdown_config();
         let mut markdown_render = MarkdownRender::new(markdown_config);
+        markdown_render.set_colocated_assets(ColocatedAssets::new(
+            &self.dir,
+            &dest,
+            &format!("/{url}"),
+        ));
         let html = markdown_render.render_html(&self.markdown);
         markdown_render.rebuild_toc_depth();
         context.insert("html", &html);
         context.insert("toc", &markdown_render.toc);
         drop(zine_data);
 
-        if let Some(path) = self.meta.path.as_ref() {
-            let mut dest = dest.to_path_buf();
-            dest.pop();
-            engine::render(
-                "article.jinja",
-                &context,
-                dest.join(path.trim_start_matches('/')),
-            )
-        } else {
-            engine::render("article.jinja", &context, dest.join(&self.meta.slug))
-        }
+        engine::render("article.jinja", &context, dest)
     }
 }
 
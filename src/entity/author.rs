@@ -74,6 +74,7 @@ impl Entity for Author {
                 ),
                 url: Some(Cow::Borrowed(&slug)),
                 image: None,
+                ..Default::default()
             },
         );
         context.insert("author", &self);
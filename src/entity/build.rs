@@ -0,0 +1,29 @@
+use serde::Deserialize;
+
+/// The build config, declared in the `[build]` table of the root `zine.toml`.
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub struct BuildConfig {
+    /// Whether to fingerprint the static assets with their content hash,
+    /// so that the CDN and browser caches never serve a stale asset.
+    #[serde(default)]
+    pub fingerprint: bool,
+    /// Whether to minify the rendered html pages, and the css and js files
+    /// in the static directory.
+    #[serde(default)]
+    pub minify: bool,
+    /// How to render the uncached url previews when building with `--offline`.
+    #[serde(default)]
+    pub offline_previews: OfflinePreviews,
+}
+
+/// The rendering of the uncached url previews in offline mode.
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OfflinePreviews {
+    /// Render a plain link card of the url.
+    #[default]
+    Link,
+    /// Fail the build with the list of the uncached urls.
+    Error,
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use anyhow::{ensure, Result};
+use serde::Deserialize;
+
+/// The HTTP client config, declared in the `[http]` table of the root `zine.toml`.
+///
+/// The client is used to fetch the url previews and lint the urls,
+/// it respects the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment variables.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub struct HttpConfig {
+    /// The `User-Agent` header of the requests.
+    #[serde(default = "HttpConfig::default_user_agent")]
+    pub user_agent: String,
+    /// The timeout (in seconds) to connect the server.
+    #[serde(default = "HttpConfig::default_connect_timeout")]
+    pub connect_timeout: u64,
+    /// The timeout (in seconds) of each read of the response.
+    #[serde(default = "HttpConfig::default_read_timeout")]
+    pub read_timeout: u64,
+    /// The max number of redirects to follow.
+    #[serde(default = "HttpConfig::default_max_redirects")]
+    pub max_redirects: u32,
+    /// The max size (in bytes) of the response body.
+    #[serde(default = "HttpConfig::default_max_body_size")]
+    pub max_body_size: u64,
+    /// The max number of concurrent requests.
+    #[serde(default = "HttpConfig::default_max_concurrency")]
+    pub max_concurrency: usize,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: Self::default_user_agent(),
+            connect_timeout: Self::DEFAULT_CONNECT_TIMEOUT,
+            read_timeout: Self::DEFAULT_READ_TIMEOUT,
+            max_redirects: Self::DEFAULT_MAX_REDIRECTS,
+            max_body_size: Self::DEFAULT_MAX_BODY_SIZE,
+            max_concurrency: Self::DEFAULT_MAX_CONCURRENCY,
+        }
+    }
+}
+
+impl HttpConfig {
+    const DEFAULT_USER_AGENT: &'static str =
+        "Mozilla/5.0 AppleWebKit/537.36 (KHTML, like Gecko) Chrome/107.0.0.0 Safari/537.36";
+    const DEFAULT_CONNECT_TIMEOUT: u64 = 10;
+    const DEFAULT_READ_TIMEOUT: u64 = 30;
+    const DEFAULT_MAX_REDIRECTS: u32 = 5;
+    const DEFAULT_MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
+    const DEFAULT_MAX_CONCURRENCY: usize = 8;
+
+    fn default_user_agent() -> String {
+        Self::DEFAULT_USER_AGENT.to_string()
+    }
+
+    fn default_connect_timeout() -> u64 {
+        Self::DEFAULT_CONNECT_TIMEOUT
+    }
+
+    fn default_read_timeout() -> u64 {
+        Self::DEFAULT_READ_TIMEOUT
+    }
+
+    fn default_max_redirects() -> u32 {
+        Self::DEFAULT_MAX_REDIRECTS
+    }
+
+    fn default_max_body_size() -> u64 {
+        Self::DEFAULT_MAX_BODY_SIZE
+    }
+
+    fn default_max_concurrency() -> usize {
+        Self::DEFAULT_MAX_CONCURRENCY
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout)
+    }
+
+    pub fn read_timeout(&self) -> Duration {
+        Duration::from_secs(self.read_timeout)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.user_agent.trim().is_empty(),
+            "The `user_agent` can't be empty"
+        );
+        ensure!(
+            self.connect_timeout > 0 && self.read_timeout > 0,
+            "The `connect_timeout` and `read_timeout` must be greater than 0"
+        );
+        ensure!(
+            self.max_body_size > 0,
+            "The `max_body_size` must be greater than 0"
+        );
+        ensure!(
+            self.max_concurrency > 0,
+            "The `max_concurrency` must be greater than 0"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+/// The image pipeline config, declared in the `[image]` table of the root `zine.toml`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub struct ImageConfig {
+    /// Whether to process local images into responsive variants.
+    #[serde(default)]
+    pub responsive: bool,
+    /// The widths (in pixels) of the resized variants.
+    /// Widths larger than the original image are skipped.
+    #[serde(default = "ImageConfig::default_widths")]
+    pub widths: Vec<u32>,
+    /// The extra formats to convert into, in addition to the original format.
+    /// Only webp by default, encoded with the same `quality` as JPEG.
+    #[serde(default = "ImageConfig::default_formats")]
+    pub formats: Vec<ImageFormat>,
+    /// The encoding quality (1-100) of lossy formats.
+    #[serde(default = "ImageConfig::default_quality")]
+    pub quality: u8,
+    /// The default `sizes` attribute of markdown images.
+    #[serde(default = "ImageConfig::default_sizes")]
+    pub sizes: String,
+    /// Whether to download the url preview images into the `static/previews` directory,
+    /// then reference the local copies instead of hotlinking the remote images.
+    /// The thumbnails of the embeds are always downloaded.
+    #[serde(default)]
+    pub download_previews: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Webp,
+    Avif,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            responsive: false,
+            widths: Self::default_widths(),
+            formats: Self::default_formats(),
+            quality: Self::default_quality(),
+            sizes: Self::default_sizes(),
+            download_previews: false,
+        }
+    }
+}
+
+impl ImageConfig {
+    const DEFAULT_QUALITY: u8 = 80;
+    const DEFAULT_SIZES: &'static str = "(min-width: 768px) 768px, 100vw";
+
+    fn default_widths() -> Vec<u32> {
+        vec![480, 960, 1440]
+    }
+
+    fn default_formats() -> Vec<ImageFormat> {
+        vec![ImageFormat::Webp]
+    }
+
+    fn default_quality() -> u8 {
+        Self::DEFAULT_QUALITY
+    }
+
+    fn default_sizes() -> String {
+        Self::DEFAULT_SIZES.to_string()
+    }
+}
@@ -146,6 +146,7 @@ impl Entity for Issue {
                 description: Cow::Owned(self.description()),
                 url: Some(Cow::Borrowed(&self.slug)),
                 image: self.cover.as_deref().map(Cow::Borrowed),
+                ..Default::default()
             },
         );
         context.insert("intro", &self.intro);
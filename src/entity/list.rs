@@ -84,6 +84,7 @@ impl<'a, E: Serialize> Entity for List<'a, E> {
                 description: Cow::Owned(String::new()),
                 url: Some(self.name.into()),
                 image: None,
+                ..Default::default()
             },
         );
         context.insert(self.name, &self.entities);
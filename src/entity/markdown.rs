@@ -1,12 +1,137 @@
-use serde::Deserialize;
+use std::collections::BTreeMap;
 
-#[derive(Debug, Deserialize, Clone)]
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+// The lucide icons, see https://lucide.dev.
+macro_rules! icon {
+    ($paths:literal) => {
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" stroke-linecap="round" stroke-linejoin="round" aria-hidden="true">"#,
+            $paths,
+            "</svg>"
+        )
+    };
+}
+
+// The builtin callout theme: (name, bg, border, icon, title).
+type BuiltinCalloutTheme = (
+    &'static str,
+    &'static str,
+    &'static str,
+    Option<&'static str>,
+    Option<&'static str>,
+);
+
+/// The builtin callout themes.
+///
+/// The admonition kinds, such as `note` and `warning`, are also themes.
+static BUILTIN_CALLOUT_THEMES: &[BuiltinCalloutTheme] = &[
+    ("blue", "#e1eaff", "#82a7fc", None, None),
+    ("grey", "#dee0e399", "#dee0e3", None, None),
+    ("gray", "#dee0e399", "#dee0e3", None, None),
+    ("red", "#fde2e2", "#f98e8b", None, None),
+    ("orange", "#feead2", "#ffba6b", None, None),
+    ("yellow", "#ffffcc", "#fff67a", None, None),
+    ("green", "#d9f5d6", "#8ee085", None, None),
+    ("purple", "#eceafe", "#ad82f7", None, None),
+    (
+        "note",
+        "#e1eaff",
+        "#82a7fc",
+        Some(icon!(
+            r#"<circle cx="12" cy="12" r="10"/><path d="M12 16v-4M12 8h.01"/>"#
+        )),
+        Some("Note"),
+    ),
+    (
+        "tip",
+        "#d9f5d6",
+        "#8ee085",
+        Some(icon!(
+            r#"<path d="M9 18h6M10 22h4M12 2a7 7 0 0 0-4 12.7V17h8v-2.3A7 7 0 0 0 12 2z"/>"#
+        )),
+        Some("Tip"),
+    ),
+    (
+        "important",
+        "#eceafe",
+        "#ad82f7",
+        Some(icon!(
+            r#"<path d="M21 15a2 2 0 0 1-2 2H7l-4 4V5a2 2 0 0 1 2-2h14a2 2 0 0 1 2 2z"/><path d="M12 7v4M12 15h.01"/>"#
+        )),
+        Some("Important"),
+    ),
+    (
+        "warning",
+        "#feead2",
+        "#ffba6b",
+        Some(icon!(
+            r#"<path d="M10.29 3.86 1.82 18a2 2 0 0 0 1.71 3h16.94a2 2 0 0 0 1.71-3L13.71 3.86a2 2 0 0 0-3.42 0z"/><path d="M12 9v4M12 17h.01"/>"#
+        )),
+        Some("Warning"),
+    ),
+    (
+        "caution",
+        "#fde2e2",
+        "#f98e8b",
+        Some(icon!(
+            r#"<path d="M7.86 2h8.28L22 7.86v8.28L16.14 22H7.86L2 16.14V7.86z"/><path d="M12 8v4M12 16h.01"/>"#
+        )),
+        Some("Caution"),
+    ),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all(deserialize = "snake_case"))]
 pub struct MarkdownConfig {
     #[serde(default = "MarkdownConfig::default_highlight_code")]
     pub highlight_code: bool,
     #[serde(default = "MarkdownConfig::default_highlight_theme")]
     pub highlight_theme: String,
+    /// How the highlighted code is styled, with inline styles or css classes.
+    #[serde(default)]
+    pub highlight_mode: HighlightMode,
+    /// The highlight theme used if the reader prefers dark color scheme.
+    /// It requires the `classes` highlight mode, which is implied if it presents.
+    #[serde(default)]
+    pub dark_highlight_theme: Option<String>,
+    /// The url of the mermaid ES module, only loaded on the pages with mermaid diagrams.
+    /// Pinned to an exact release by default, change it to self-host the script.
+    #[serde(default = "MarkdownConfig::default_mermaid_script")]
+    pub mermaid_script: String,
+    /// Whether to render the footnotes as sidenotes in the margin on wide screens.
+    #[serde(default)]
+    pub sidenotes: bool,
+    /// The named callout themes, which override the builtin themes of the same name.
+    #[serde(default)]
+    pub callout_themes: BTreeMap<String, CalloutTheme>,
+}
+
+/// The callout theme, used as `theme: <name>` of the callout block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CalloutTheme {
+    /// The background color.
+    pub bg: String,
+    /// The border color.
+    pub border: String,
+    /// The inline svg or the image url.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// The default title.
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightMode {
+    /// Inline the colors on every span.
+    #[default]
+    Inline,
+    /// Emit css classed spans, the colors are defined in the generated stylesheet.
+    Classes,
 }
 
 impl Default for MarkdownConfig {
@@ -14,18 +139,157 @@ impl Default for MarkdownConfig {
         Self {
             highlight_code: true,
             highlight_theme: Self::default_highlight_theme(),
+            highlight_mode: HighlightMode::default(),
+            dark_highlight_theme: None,
+            mermaid_script: Self::default_mermaid_script(),
+            sidenotes: false,
+            callout_themes: BTreeMap::new(),
         }
     }
 }
 
 impl MarkdownConfig {
     const DEFAULT_HIGHLIGHT_THEME: &'static str = "monokai";
+    const DEFAULT_MERMAID_SCRIPT: &'static str =
+        "https://cdn.jsdelivr.net/npm/mermaid@10.9.1/dist/mermaid.esm.min.mjs";
+    pub const DEFAULT_CALLOUT_THEME: &'static str = "blue";
+
+    /// Get the callout theme by name, the project themes override the builtin themes.
+    ///
+    /// The missing icon and title of the overridden theme fallback to the builtin one.
+    pub fn callout_theme(&self, name: &str) -> Option<CalloutTheme> {
+        let builtin = BUILTIN_CALLOUT_THEMES
+            .iter()
+            .find(|(builtin, ..)| *builtin == name)
+            .map(|(_, bg, border, icon, title)| CalloutTheme {
+                bg: bg.to_string(),
+                border: border.to_string(),
+                icon: icon.map(str::to_owned),
+                title: title.map(str::to_owned),
+            });
+        match (self.callout_themes.get(name).cloned(), builtin) {
+            (Some(mut theme), Some(builtin)) => {
+                theme.icon = theme.icon.or(builtin.icon);
+                theme.title = theme.title.or(builtin.title);
+                Some(theme)
+            }
+            (theme, builtin) => theme.or(builtin),
+        }
+    }
+
+    /// Validate the markdown config, such as the colors of the callout themes.
+    pub fn validate(&self) -> Result<()> {
+        for (name, theme) in &self.callout_themes {
+            ensure!(
+                !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_'),
+                "Invalid callout theme name `{name}`, only lowercase letters, digits, `-` and `_` are allowed"
+            );
+            for (field, color) in [("bg", &theme.bg), ("border", &theme.border)] {
+                ensure!(
+                    is_css_color(color),
+                    "Invalid `{field}` color `{color}` of callout theme `{name}`"
+                );
+            }
+            if let Some(icon) = theme.icon.as_deref() {
+                let icon = icon.trim();
+                ensure!(
+                    (icon.starts_with("<svg") && icon.ends_with("</svg>"))
+                        || (!icon.is_empty() && !icon.contains(['<', '>', '"'])),
+                    "Invalid icon of callout theme `{name}`, it should be an inline svg or an image url"
+                );
+            }
+        }
+        Ok(())
+    }
 
     fn default_highlight_theme() -> String {
         Self::DEFAULT_HIGHLIGHT_THEME.to_string()
     }
 
+    fn default_mermaid_script() -> String {
+        Self::DEFAULT_MERMAID_SCRIPT.to_string()
+    }
+
     fn default_highlight_code() -> bool {
         true
     }
 }
+
+// Whether the color is a hex color, a color function or a named color,
+// the color is rendered into the inline style.
+fn is_css_color(color: &str) -> bool {
+    if let Some(hex) = color.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    if let Some((function, args)) = color.split_once('(') {
+        return matches!(
+            function,
+            "rgb" | "rgba" | "hsl" | "hsla" | "hwb" | "lab" | "lch" | "oklab" | "oklch" | "var"
+        ) && args.strip_suffix(')').is_some_and(|args| {
+            args.chars()
+                .all(|c| c.is_ascii_alphanumeric() || " .,%/+-".contains(c))
+        });
+    }
+    !color.is_empty() && color.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{is_css_color, MarkdownConfig};
+
+    #[test_case("#fff", true)]
+    #[test_case("#12345678", true)]
+    #[test_case("rgb(1 2 3 / 50%)", true)]
+    #[test_case("var(--primary-color)", true)]
+    #[test_case("teal", true)]
+    #[test_case("#12345", false)]
+    #[test_case("red; color: blue", false)]
+    #[test_case("url(x)", false)]
+    #[test_case("", false)]
+    fn test_is_css_color(color: &str, expected: bool) {
+        assert_eq!(is_css_color(color), expected);
+    }
+
+    #[test]
+    fn test_callout_themes() {
+        let config: MarkdownConfig = toml::from_str(
+            r##"
+            [callout_themes.warning]
+            bg = "#fff7ed"
+            border = "#fb923c"
+
+            [callout_themes.brand]
+            bg = "#fdf2f8"
+            border = "#ec4899"
+            icon = "/static/brand.svg"
+            title = "Brand"
+            "##,
+        )
+        .unwrap();
+        config.validate().unwrap();
+
+        let warning = config.callout_theme("warning").unwrap();
+        assert_eq!(warning.bg, "#fff7ed");
+        assert_eq!(warning.title.as_deref(), Some("Warning"));
+        assert!(warning.icon.is_some());
+        assert_eq!(
+            config.callout_theme("brand").unwrap().title.as_deref(),
+            Some("Brand")
+        );
+        assert_eq!(config.callout_theme("red").unwrap().bg, "#fde2e2");
+        assert!(config.callout_theme("unknown").is_none());
+
+        let config: MarkdownConfig =
+            toml::from_str("[callout_themes.bad]\nbg = \"red\"\nborder = \"red;x\"").unwrap();
+        assert!(config.validate().is_err());
+        assert!(toml::from_str::<MarkdownConfig>(
+            "[callout_themes.bad]\nbg = \"red\"\nborder = \"red\"\ncolor = \"red\""
+        )
+        .is_err());
+    }
+}
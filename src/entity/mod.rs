@@ -5,6 +5,9 @@ use tera::Context;
 
 mod article;
 mod author;
+mod build;
+mod http;
+mod image;
 mod issue;
 mod list;
 mod markdown;
@@ -17,9 +20,12 @@ mod zine;
 pub use self::zine::Zine;
 pub use article::{Article, MetaArticle};
 pub use author::{Author, AuthorId};
+pub use build::{BuildConfig, OfflinePreviews};
+pub use http::HttpConfig;
+pub use image::{ImageConfig, ImageFormat};
 pub use issue::Issue;
 pub use list::List;
-pub use markdown::MarkdownConfig;
+pub use markdown::{CalloutTheme, HighlightMode, MarkdownConfig};
 pub use page::Page;
 pub use site::Site;
 pub use theme::Theme;
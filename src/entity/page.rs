@@ -48,6 +48,7 @@ impl Entity for Page {
                 description: Cow::Owned(markdown::extract_description(&self.markdown)),
                 url: Some(Cow::Owned(self.slug())),
                 image: None,
+                ..Default::default()
             },
         );
         context.insert("page", &self);
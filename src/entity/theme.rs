@@ -4,10 +4,13 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
 use super::Entity;
+use crate::themes::ThemePackage;
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all(deserialize = "snake_case"))]
 pub struct Theme {
+    // The theme package name or path.
+    pub name: Option<String>,
     // The primary color.
     #[serde(default = "Theme::default_primary_color")]
     pub primary_color: String,
@@ -32,11 +35,18 @@ pub struct Theme {
     pub article_extend_template: Option<String>,
     pub default_cover: Option<String>,
     pub default_avatar: Option<String>,
+    // The dark palette, used if the reader prefers dark color scheme.
+    #[serde(default)]
+    pub dark: Option<DarkTheme>,
+    // The located theme package of `name`.
+    #[serde(skip)]
+    pub package: Option<ThemePackage>,
 }
 
 impl Default for Theme {
     fn default() -> Self {
         Self {
+            name: None,
             primary_color: Self::default_primary_color(),
             main_color: Self::default_main_color(),
             link_color: Self::default_link_color(),
@@ -47,6 +57,8 @@ impl Default for Theme {
             article_extend_template: None,
             default_cover: None,
             default_avatar: None,
+            dark: None,
+            package: None,
         }
     }
 }
@@ -54,6 +66,7 @@ impl Default for Theme {
 impl std::fmt::Debug for Theme {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Theme")
+            .field("name", &self.name)
             .field("primary_color", &self.primary_color)
             .field("main_color", &self.main_color)
             .field("link_color", &self.link_color)
@@ -67,6 +80,7 @@ impl std::fmt::Debug for Theme {
             )
             .field("default_cover", &self.default_cover)
             .field("default_avatar", &self.default_avatar)
+            .field("dark", &self.dark)
             .finish()
     }
 }
@@ -94,6 +108,67 @@ impl Theme {
     }
 }
 
+/// The dark palette of [`Theme`], declared in the `[theme.dark]` table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub struct DarkTheme {
+    // The primary color.
+    #[serde(default = "DarkTheme::default_primary_color")]
+    pub primary_color: String,
+    // The text main color.
+    #[serde(default = "DarkTheme::default_main_color")]
+    pub main_color: String,
+    // The article's link color.
+    #[serde(default = "DarkTheme::default_link_color")]
+    pub link_color: String,
+    // The background color.
+    #[serde(default = "DarkTheme::default_secondary_color")]
+    pub secondary_color: String,
+    // The background color of cards, such as the article container.
+    #[serde(default = "DarkTheme::default_surface_color")]
+    pub surface_color: String,
+}
+
+impl Default for DarkTheme {
+    fn default() -> Self {
+        Self {
+            primary_color: Self::default_primary_color(),
+            main_color: Self::default_main_color(),
+            link_color: Self::default_link_color(),
+            secondary_color: Self::default_secondary_color(),
+            surface_color: Self::default_surface_color(),
+        }
+    }
+}
+
+impl DarkTheme {
+    const DEFAULT_PRIMARY_COLOR: &'static str = "#1e3a8a";
+    const DEFAULT_MAIN_COLOR: &'static str = "#f8fafc";
+    const DEFAULT_LINK_COLOR: &'static str = "#60a5fa";
+    const DEFAULT_SECONDARY_COLOR: &'static str = "#0f172a";
+    const DEFAULT_SURFACE_COLOR: &'static str = "#1e293b";
+
+    fn default_primary_color() -> String {
+        Self::DEFAULT_PRIMARY_COLOR.to_string()
+    }
+
+    fn default_main_color() -> String {
+        Self::DEFAULT_MAIN_COLOR.to_string()
+    }
+
+    fn default_link_color() -> String {
+        Self::DEFAULT_LINK_COLOR.to_string()
+    }
+
+    fn default_secondary_color() -> String {
+        Self::DEFAULT_SECONDARY_COLOR.to_string()
+    }
+
+    fn default_surface_color() -> String {
+        Self::DEFAULT_SURFACE_COLOR.to_string()
+    }
+}
+
 impl Entity for Theme {
     fn parse(&mut self, source: &Path) -> Result<()> {
         if self.default_cover.is_none() {
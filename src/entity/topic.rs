@@ -33,6 +33,7 @@ impl Entity for Topic {
                 description: Cow::Borrowed(self.description.as_deref().unwrap_or("")),
                 url: Some(format!("/topic/{}", self.id.to_lowercase()).into()),
                 image: None,
+                ..Default::default()
             },
         );
         context.insert("topic", &self);
@@ -12,9 +12,12 @@ use std::{
 use tera::Context;
 use walkdir::WalkDir;
 
-use crate::{data, engine, error::ZineError, feed::FeedEntry, Entity};
+use crate::{data, engine, error::ZineError, feed::FeedEntry, themes::ThemePackage, Entity};
 
-use super::{Author, Issue, List, MarkdownConfig, MetaArticle, Page, Site, Theme, Topic};
+use super::{
+    Author, BuildConfig, HighlightMode, HttpConfig, ImageConfig, Issue, List, MarkdownConfig,
+    MetaArticle, Page, Site, Theme, Topic,
+};
 
 /// The root zine entity config.
 ///
@@ -36,6 +39,15 @@ pub struct Zine {
     #[serde(default)]
     #[serde(rename = "markdown")]
     pub markdown_config: MarkdownConfig,
+    #[serde(default)]
+    #[serde(rename = "image")]
+    pub image_config: ImageConfig,
+    #[serde(default)]
+    #[serde(rename = "build")]
+    pub build_config: BuildConfig,
+    #[serde(default)]
+    #[serde(rename = "http")]
+    pub http_config: HttpConfig,
 }
 
 impl std::fmt::Debug for Zine {
@@ -60,11 +72,12 @@ struct ArticleRef<'a> {
 impl Zine {
     /// Parse Zine instance from the root zine.toml file.li
     pub fn parse_from_toml<P: AsRef<Path>>(source: P) -> Result<Zine> {
-        let source = source.as_ref().join(crate::ZINE_FILE);
+        let root = source.as_ref();
+        let source = root.join(crate::ZINE_FILE);
         let content = fs::read_to_string(&source)
             .with_context(|| format!("Failed to read `{}`", source.display()))?;
 
-        Ok(toml::from_str::<Zine>(&content).map_err(|err| {
+        let mut zine = toml::from_str::<Zine>(&content).map_err(|err| {
             let value = toml::from_str::<toml::Value>(&content)
                 .unwrap_or_else(|_| panic!("Parse `{}` failed", source.display()));
             if value.get("site").is_some() {
@@ -72,7 +85,14 @@ impl Zine {
             } else {
                 ZineError::NotRootTomlFile
             }
-        })?)
+        })?;
+
+        // Merge the theme config over the default theme config of theme package.
+        if let Some(name) = zine.theme.name.as_deref() {
+            let value = toml::from_str::<toml::Value>(&content)?;
+            zine.theme = ThemePackage::locate(root, name)?.merge_theme(value.get("theme"))?;
+        }
+        Ok(zine)
     }
 
     /// Parsing issue entities from dir.
@@ -281,6 +301,13 @@ impl Zine {
 impl Entity for Zine {
     fn parse(&mut self, source: &Path) -> Result<()> {
         self.theme.parse(source)?;
+        self.markdown_config
+            .validate()
+            .context("Invalid [markdown] config of root `zine.toml`")?;
+        // The dark highlight theme is switched by css classes.
+        if self.markdown_config.dark_highlight_theme.is_some() {
+            self.markdown_config.highlight_mode = HighlightMode::Classes;
+        }
         {
             let mut zine_data = data::write();
             zine_data
@@ -357,6 +384,7 @@ impl Entity for Zine {
         );
         context.insert("theme", &self.theme);
         context.insert("site", &self.site);
+        context.insert("markdown", &self.markdown_config);
 
         // Render all authors pages.
         let authors = self.authors();
@@ -6,4 +6,6 @@ pub enum ZineError {
     InvalidRootTomlFile(#[from] toml::de::Error),
     #[error("Not a root `zine.toml`, maybe it a `zine.toml` for issue?")]
     NotRootTomlFile,
+    #[error("`{0}` has been redirected more than {1} times")]
+    TooManyRedirects(String, u32),
 }
@@ -0,0 +1,120 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use parking_lot::{RwLock, RwLockReadGuard};
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use walkdir::WalkDir;
+
+use crate::helpers;
+
+/// The file name of the asset manifest, written into the dest directory.
+static MANIFEST_FILE: &str = "asset-manifest.json";
+
+/// The asset url and fingerprinted url map, such as:
+/// `/static/zine.css` => `/static/zine.3f8a0c2b9d4e1f7a.css`.
+pub type AssetManifest = BTreeMap<String, String>;
+
+static MANIFEST: Lazy<RwLock<AssetManifest>> = Lazy::new(Default::default);
+
+/// Get the asset manifest of current build under read lock.
+pub fn manifest() -> RwLockReadGuard<'static, AssetManifest> {
+    MANIFEST.read()
+}
+
+/// Clear the asset manifest, all asset urls are kept as is.
+pub fn clear() {
+    MANIFEST.write().clear();
+}
+
+/// Fingerprint all files in the `static` directory of `dest` with their content hash,
+/// then write the asset manifest.
+///
+/// The fingerprinted files are copied alongside the original files, so the
+/// references we don't rewrite (such as `url()` in css) still work.
+pub fn fingerprint_static_assets(dest: &Path) -> Result<()> {
+    let static_dir = dest.join("static");
+    let manifest_path = dest.join(MANIFEST_FILE);
+
+    // Remove the fingerprinted files of the previous build,
+    // avoid fingerprinting them again.
+    if manifest_path.exists() {
+        let previous = serde_json::from_str::<AssetManifest>(&fs::read_to_string(&manifest_path)?)?;
+        for url in previous.values() {
+            let _ = fs::remove_file(dest.join(url.trim_start_matches('/')));
+        }
+    }
+
+    let manifest = WalkDir::new(&static_dir)
+        .into_iter()
+        .par_bridge()
+        .try_fold(AssetManifest::new, |mut manifest, entry| {
+            let entry = entry?;
+            let path = entry.path();
+            // The processed images have been named with content hash.
+            if !path.is_file() || path.starts_with(static_dir.join("_images")) {
+                return anyhow::Ok(manifest);
+            }
+
+            let bytes = fs::read(path)?;
+            let file_name = fingerprinted_file_name(
+                &entry.file_name().to_string_lossy(),
+                &helpers::content_hash(&bytes),
+            );
+            let fingerprinted = path.with_file_name(file_name);
+            fs::write(&fingerprinted, bytes)?;
+            manifest.insert(asset_url(dest, path), asset_url(dest, &fingerprinted));
+            anyhow::Ok(manifest)
+        })
+        .try_reduce(AssetManifest::new, |mut manifest, chunk| {
+            manifest.extend(chunk);
+            anyhow::Ok(manifest)
+        })?;
+
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    *MANIFEST.write() = manifest;
+    Ok(())
+}
+
+// Get the root path url of the file `path` in `dest` directory.
+fn asset_url(dest: &Path, path: &Path) -> String {
+    let relative_path = path
+        .strip_prefix(dest)
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let segments = relative_path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    format!("/{}", segments.join("/"))
+}
+
+// Insert `hash` before the extension of `file_name`.
+fn fingerprinted_file_name(file_name: &str, hash: &str) -> String {
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{stem}.{hash}.{extension}"),
+        _ => format!("{file_name}.{hash}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::fingerprinted_file_name;
+
+    #[test_case("zine.css", "zine.0123456789abcdef.css")]
+    #[test_case("medium-zoom.min.js", "medium-zoom.min.0123456789abcdef.js")]
+    #[test_case("LICENSE", "LICENSE.0123456789abcdef")]
+    #[test_case(".nojekyll", ".nojekyll.0123456789abcdef")]
+    fn test_fingerprinted_file_name(file_name: &str, expected: &str) {
+        assert_eq!(
+            fingerprinted_file_name(file_name, "0123456789abcdef"),
+            expected
+        );
+    }
+}
@@ -1,15 +1,10 @@
 use anyhow::Result;
-use hyper::{
-    body::{self, Buf},
-    http::HeaderValue,
-    Client, Request, Uri,
-};
-use hyper_tls::HttpsConnector;
 use rayon::iter::{ParallelBridge, ParallelIterator};
+use sha2::{Digest, Sha256};
 use std::{
     collections::HashMap,
     fs,
-    io::{self, ErrorKind, Read},
+    io::{self, ErrorKind},
     path::Path,
     process::Command,
 };
@@ -60,29 +55,19 @@ pub fn split_styles(style: &str) -> HashMap<&str, &str> {
         .collect::<HashMap<_, _>>()
 }
 
-pub async fn fetch_url(url: &str) -> Result<impl Read> {
-    let client = Client::builder().build::<_, hyper::Body>(HttpsConnector::new());
-    let mut req = Request::new(Default::default());
-    *req.uri_mut() = url.parse::<Uri>()?;
-    req.headers_mut().insert(
-        "User-Agent",
-        HeaderValue::from_static(
-            "Mozilla/5.0 AppleWebKit/537.36 (KHTML, like Gecko) Chrome/107.0.0.0 Safari/537.36",
-        ),
-    );
-    let resp = client.request(req).await?;
-    if resp.status().is_redirection() {
-        if let Some(location) = resp.headers().get("Location") {
-            println!(
-                "Warning: url `{url}` has been redirected to `{}`",
-                location.to_str()?,
-            );
-        } else {
-            println!("Warning: url `{url}` has been redirected");
-        }
-    }
-    let bytes = body::to_bytes(resp.into_body()).await?;
-    Ok(bytes.reader())
+/// Get the short content hash of `bytes`, which is the first 16 hex chars of the sha256 digest.
+///
+/// ```rust
+/// use zine::helpers::content_hash;
+///
+/// assert_eq!(content_hash(b"zine"), content_hash(b"zine"));
+/// assert_ne!(content_hash(b"zine"), content_hash(b"Zine"));
+/// assert_eq!(content_hash(b"zine").len(), 16);
+/// ```
+pub fn content_hash(bytes: &[u8]) -> String {
+    let mut hash = format!("{:x}", Sha256::digest(bytes));
+    hash.truncate(16);
+    hash
 }
 
 /// Copy directory recursively.
@@ -155,4 +140,27 @@ pub mod serde_date {
                 .map_err(|e| E::custom(format!("The date value {} is invalid: {}", v, e)))
         }
     }
+
+    /// The optional date, which should be used with `#[serde(default)]`.
+    pub mod option {
+        use serde::{de, Serializer};
+        use time::Date;
+
+        pub fn serialize<S: Serializer>(
+            date: &Option<Date>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match date {
+                Some(date) => super::serialize(date, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(d: D) -> Result<Option<Date>, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            super::deserialize(d).map(Some)
+        }
+    }
 }
@@ -9,7 +9,9 @@ use markup5ever_rcdom::{Handle, NodeData, RcDom};
 
 use serde::Serialize;
 
-use crate::helpers;
+use crate::{fingerprint::AssetManifest, helpers};
+
+static HTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
 
 /// The meta info of the HTML page.
 #[derive(Debug, Default, Serialize)]
@@ -18,6 +20,8 @@ pub struct Meta<'a> {
     pub description: Cow<'a, str>,
     pub url: Option<Cow<'a, str>>,
     pub image: Option<Cow<'a, str>>,
+    pub site_name: Option<Cow<'a, str>>,
+    pub favicon: Option<Cow<'a, str>>,
 }
 
 impl<'a> Meta<'a> {
@@ -25,6 +29,8 @@ impl<'a> Meta<'a> {
         !self.title.is_empty()
             && !self.description.is_empty()
             && matches!(&self.image, Some(image) if !image.is_empty())
+            && self.site_name.is_some()
+            && self.favicon.is_some()
     }
 
     pub fn truncate(&mut self) {
@@ -34,23 +40,31 @@ impl<'a> Meta<'a> {
 }
 
 /// Rewrite root path URL in `raw_html` with `site_url` and `cdn_url`.
+///
+/// The asset URLs in `manifest` are rewritten to their fingerprinted URLs beforehand.
 pub fn rewrite_html_base_url(
     raw_html: &[u8],
     site_url: Option<&str>,
     cdn_url: Option<&str>,
+    manifest: Option<&AssetManifest>,
 ) -> Result<Vec<u8>> {
+    let rewrite_url = |url: &str| {
+        let fingerprinted_url = manifest.and_then(|manifest| manifest.get(url));
+        let url = fingerprinted_url.map(String::as_str).unwrap_or(url);
+        if let (Some(url), Some(cdn_url)) = (url.strip_prefix("/static"), cdn_url) {
+            Some(format!("{}{}", &cdn_url, url))
+        } else if let (true, Some(site_url)) = (url.starts_with('/'), site_url) {
+            Some(format!("{}{}", &site_url, url))
+        } else {
+            // no need to rewrite base url
+            fingerprinted_url.cloned()
+        }
+    };
     let rewrite_url_in_attr = |el: &mut Element, attr_name: &str| {
-        if let Some(attr) = el.get_attribute(attr_name) {
-            let dest_url =
-                if let (Some(attr), Some(cdn_url)) = (attr.strip_prefix("/static"), cdn_url) {
-                    format!("{}{}", &cdn_url, attr)
-                } else if let (true, Some(site_url)) = (attr.starts_with('/'), site_url) {
-                    format!("{}{}", &site_url, attr)
-                } else {
-                    // no need to rewrite
-                    return;
-                };
-
+        if let Some(dest_url) = el
+            .get_attribute(attr_name)
+            .and_then(|attr| rewrite_url(&attr))
+        {
             el.set_attribute(attr_name, &dest_url)
                 .expect("Set attribute failed");
         }
@@ -110,6 +124,29 @@ pub fn rewrite_html_base_url(
                     }
                     Ok(())
                 }),
+                // Rewrite every candidate url of srcset, such as:
+                // srcset="/static/a-480w.webp 480w, /static/a-960w.webp 960w"
+                element!("img[srcset], source[srcset]", |el| {
+                    if let Some(srcset) = el.get_attribute("srcset") {
+                        let new_srcset = srcset
+                            .split(',')
+                            .map(|candidate| {
+                                let candidate = candidate.trim();
+                                let (url, descriptor) =
+                                    candidate.split_once(' ').unwrap_or((candidate, ""));
+                                match rewrite_url(url) {
+                                    Some(url) if descriptor.is_empty() => url,
+                                    Some(url) => format!("{url} {descriptor}"),
+                                    None => candidate.to_owned(),
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        el.set_attribute("srcset", &new_srcset)
+                            .expect("Rewrite srcset failed.");
+                    }
+                    Ok(())
+                }),
                 element!("meta[content]", |el| {
                     rewrite_url_in_attr(el, "content");
                     Ok(())
@@ -196,13 +233,30 @@ fn walk(handle: &Handle, meta: &mut Meta) {
                             meta.image = Some(Cow::Owned(image.to_owned()));
                         }
                     }
+                    Some("og:site_name") if meta.site_name.is_none() => {
+                        if let Some(site_name) = get_attribute(attrs, "content") {
+                            meta.site_name = Some(Cow::Owned(site_name.to_owned()));
+                        }
+                    }
                     _ => {}
                 }
             }
-            "link" => {
-                // TODO: Extract favicon from <link> tag
+            "link" if meta.favicon.is_none() => {
+                // <link rel="icon" href="/favicon.ico">
+                // <link rel="shortcut icon" href="/favicon.ico">
+                let attrs = &*attrs.borrow();
+                let is_icon = get_attribute(attrs, "rel").is_some_and(|rel| {
+                    rel.split_ascii_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("icon"))
+                });
+                if is_icon {
+                    if let Some(href) = get_attribute(attrs, "href") {
+                        meta.favicon = Some(Cow::Owned(href.to_owned()));
+                    }
+                }
             }
-            "title" => {
+            // The <title> of the inline svg isn't the page title.
+            "title" if &*name.ns == HTML_NAMESPACE => {
                 // Extract <title> tag.
                 // Some title tag may have multiple empty text child nodes,
                 // we need handle this case:
@@ -241,17 +295,34 @@ fn walk(handle: &Handle, meta: &mut Meta) {
 
 #[cfg(test)]
 mod tests {
-    use super::rewrite_html_base_url;
+    use super::{parse_html_meta, rewrite_html_base_url};
+    use crate::fingerprint::AssetManifest;
     use test_case::test_case;
 
     const SITE_URL: &str = "https://github.com";
     const CDN_URL: &str = "https://cdn-example.net";
 
+    #[test]
+    fn test_parse_html_meta() {
+        let html = r#"<html><head>
+            <title> Zine </title>
+            <meta property="og:site_name" content="Zineland">
+            <link rel="stylesheet" href="/zine.css">
+            <link rel="Shortcut Icon" href="/favicon.ico">
+            <link rel="icon" href="/other.png">
+            </head><body><svg><title>Logo</title></svg></body></html>"#;
+        let meta = parse_html_meta(html.as_bytes());
+        assert_eq!(meta.title, "Zine");
+        assert_eq!(meta.site_name.as_deref(), Some("Zineland"));
+        assert_eq!(meta.favicon.as_deref(), Some("/favicon.ico"));
+    }
+
     #[test_case(r#"<body><div class="bg-primary text-main" style="background-image: url('/test.png');"></div></body>"#)]
     fn test_rewrite_background_image_url(html: &str) {
         assert_eq!(
             String::from_utf8_lossy(
-                &rewrite_html_base_url(html.as_bytes(), Some(SITE_URL), Some(CDN_URL)).unwrap()
+                &rewrite_html_base_url(html.as_bytes(), Some(SITE_URL), Some(CDN_URL), None)
+                    .unwrap()
             ),
             html.replace("/test.png", &format!("{}/test.png", SITE_URL))
         );
@@ -262,7 +333,8 @@ mod tests {
     fn test_rewrite_cdn_background_image_url(html: &str) {
         assert_eq!(
             String::from_utf8_lossy(
-                &rewrite_html_base_url(html.as_bytes(), Some(SITE_URL), Some(CDN_URL)).unwrap()
+                &rewrite_html_base_url(html.as_bytes(), Some(SITE_URL), Some(CDN_URL), None)
+                    .unwrap()
             ),
             html.replace("/static/test.png", &format!("{}/test.png", CDN_URL))
         );
@@ -283,7 +355,8 @@ mod tests {
                 &rewrite_html_base_url(
                     html.replace("{}", path).as_bytes(),
                     Some(SITE_URL),
-                    Some(CDN_URL)
+                    Some(CDN_URL),
+                    None
                 )
                 .unwrap()
             ),
@@ -307,7 +380,8 @@ mod tests {
                 &rewrite_html_base_url(
                     html.replace("{}", &whole_url).as_bytes(),
                     Some(SITE_URL),
-                    Some(CDN_URL)
+                    Some(CDN_URL),
+                    None
                 )
                 .unwrap()
             ),
@@ -328,7 +402,8 @@ mod tests {
                 &rewrite_html_base_url(
                     html.replace("{}", path).as_bytes(),
                     Some(SITE_URL),
-                    Some(CDN_URL)
+                    Some(CDN_URL),
+                    None
                 )
                 .unwrap()
             ),
@@ -348,7 +423,8 @@ mod tests {
                 &rewrite_html_base_url(
                     html.replace("{}", path).as_bytes(),
                     Some(SITE_URL),
-                    Some(CDN_URL)
+                    Some(CDN_URL),
+                    None
                 )
                 .unwrap()
             ),
@@ -369,7 +445,8 @@ mod tests {
                 &rewrite_html_base_url(
                     html.replace("{}", &whole_url).as_bytes(),
                     Some(SITE_URL),
-                    Some(CDN_URL)
+                    Some(CDN_URL),
+                    None
                 )
                 .unwrap()
             ),
@@ -377,6 +454,31 @@ mod tests {
         );
     }
 
+    #[test_case(
+        r#"<img srcset="/static/a-480w.webp 480w, /static/a-960w.webp 960w">"#,
+        r#"<img srcset="https://cdn-example.net/a-480w.webp 480w, https://cdn-example.net/a-960w.webp 960w">"#;
+        "img cdn"
+    )]
+    #[test_case(
+        r#"<source srcset="/a-480w.webp 480w,/a.webp 2x">"#,
+        r#"<source srcset="https://github.com/a-480w.webp 480w, https://github.com/a.webp 2x">"#;
+        "source site"
+    )]
+    #[test_case(
+        r#"<img srcset="https://example.com/a.webp 480w, a.webp">"#,
+        r#"<img srcset="https://example.com/a.webp 480w, a.webp">"#;
+        "not rewrite"
+    )]
+    fn test_rewrite_srcset(html: &str, expected: &str) {
+        assert_eq!(
+            String::from_utf8_lossy(
+                &rewrite_html_base_url(html.as_bytes(), Some(SITE_URL), Some(CDN_URL), None)
+                    .unwrap()
+            ),
+            expected
+        );
+    }
+
     #[test_case("<link rel=\"stylesheet\" src=\"{}\"/>", "static/hello.css"; "link")]
     #[test_case("<img src=\"{}\"/>", "static/hello.png"; "img")]
     #[test_case("<script src=\"{}\"/>", "static/hello.js"; "script")]
@@ -389,11 +491,49 @@ mod tests {
                 &rewrite_html_base_url(
                     html.replace("{}", path).as_bytes(),
                     Some(SITE_URL),
-                    Some(CDN_URL)
+                    Some(CDN_URL),
+                    None
                 )
                 .unwrap()
             ),
             html.replace("{}", path)
         );
     }
+
+    #[test_case(
+        r#"<link rel="stylesheet" href="/static/zine.css" />"#, None, None,
+        r#"<link rel="stylesheet" href="/static/zine.0123456789abcdef.css" />"#; "link")]
+    #[test_case(
+        r#"<script src="/static/zine.js" />"#, None, None,
+        r#"<script src="/static/zine.0123456789abcdef.js" />"#; "script")]
+    #[test_case(
+        r#"<link rel="stylesheet" href="/static/zine.css" />"#, Some(SITE_URL), None,
+        r#"<link rel="stylesheet" href="https://github.com/static/zine.0123456789abcdef.css" />"#; "link site")]
+    #[test_case(
+        r#"<script src="/static/zine.js" />"#, Some(SITE_URL), Some(CDN_URL),
+        r#"<script src="https://cdn-example.net/zine.0123456789abcdef.js" />"#; "script cdn")]
+    fn test_rewrite_fingerprinted_url(
+        html: &str,
+        site_url: Option<&str>,
+        cdn_url: Option<&str>,
+        expected: &str,
+    ) {
+        let manifest = AssetManifest::from([
+            (
+                "/static/zine.css".to_owned(),
+                "/static/zine.0123456789abcdef.css".to_owned(),
+            ),
+            (
+                "/static/zine.js".to_owned(),
+                "/static/zine.0123456789abcdef.js".to_owned(),
+            ),
+        ]);
+        assert_eq!(
+            String::from_utf8_lossy(
+                &rewrite_html_base_url(html.as_bytes(), site_url, cdn_url, Some(&manifest))
+                    .unwrap()
+            ),
+            expected
+        );
+    }
 }
@@ -0,0 +1,533 @@
+use std::{env, io::Read, sync::Arc};
+
+use anyhow::{bail, Context as _, Result};
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use native_tls::TlsConnector;
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use tokio::sync::Semaphore;
+use ureq::{Agent, AgentBuilder, ErrorKind, Proxy};
+use url::Url;
+
+use crate::{entity::HttpConfig, error::ZineError};
+
+/// The max bytes to sniff the charset of the `<meta>` tag.
+const META_SNIFF_SIZE: usize = 1024;
+
+static CLIENT: OnceCell<RwLock<Arc<HttpClient>>> = OnceCell::new();
+
+/// Initialize the shared HTTP client with the `[http]` config.
+pub fn init(config: &HttpConfig) -> Result<()> {
+    config
+        .validate()
+        .context("Invalid [http] config of root `zine.toml`")?;
+    let client = Arc::new(HttpClient::new(config)?);
+    match CLIENT.get() {
+        Some(lock) => *lock.write() = client,
+        None => {
+            CLIENT.get_or_init(|| RwLock::new(client));
+        }
+    }
+    Ok(())
+}
+
+// The shared HTTP client, fallback to the default config if it isn't initialized.
+fn client() -> Arc<HttpClient> {
+    let client = CLIENT.get_or_init(|| {
+        let client =
+            HttpClient::new(&HttpConfig::default()).expect("Failed to create the HTTP client");
+        RwLock::new(Arc::new(client))
+    });
+    Arc::clone(&client.read())
+}
+
+/// The fetched HTML page.
+pub struct HtmlPage {
+    /// The final url after redirects.
+    pub url: String,
+    /// The HTML decoded from the page charset.
+    pub html: String,
+}
+
+/// Fetch the HTML page of `url`.
+pub async fn fetch_html(url: &str) -> Result<HtmlPage> {
+    client().fetch_html(url).await
+}
+
+/// Fetch the raw bytes of `url`, such as an image.
+pub async fn fetch_bytes(url: &str) -> Result<Vec<u8>> {
+    client().fetch_bytes(url).await
+}
+
+/// Request `url` with the `HEAD` method, return the status code
+/// and whether the url has been redirected.
+///
+/// Return [`ZineError::TooManyRedirects`] if the redirects exceed the `max_redirects`.
+pub async fn head(url: &str) -> Result<(u16, bool)> {
+    client().head(url).await
+}
+
+pub(crate) struct HttpClient {
+    direct: Agent,
+    // The agents with the proxy of `HTTP_PROXY` and `HTTPS_PROXY`.
+    http_proxy: Option<Agent>,
+    https_proxy: Option<Agent>,
+    // The hosts bypass the proxy, declared in `NO_PROXY`.
+    no_proxy: Vec<String>,
+    max_body_size: u64,
+    max_redirects: u32,
+    // Limit the concurrent requests.
+    semaphore: Semaphore,
+}
+
+struct Response {
+    // The final url after redirects.
+    url: String,
+    status: u16,
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+impl HttpClient {
+    pub(crate) fn new(config: &HttpConfig) -> Result<Self> {
+        let tls_connector = Arc::new(TlsConnector::new()?);
+        let agent = |proxy: Option<String>| -> Result<Agent> {
+            let mut builder = AgentBuilder::new()
+                .timeout_connect(config.connect_timeout())
+                .timeout_read(config.read_timeout())
+                // The ureq counts the last response into the redirects limit.
+                .redirects(match config.max_redirects {
+                    0 => 0,
+                    max_redirects => max_redirects + 1,
+                })
+                .user_agent(&config.user_agent)
+                .tls_connector(Arc::clone(&tls_connector));
+            if let Some(proxy) = proxy {
+                let proxy =
+                    Proxy::new(&proxy).with_context(|| format!("Invalid proxy `{proxy}`"))?;
+                builder = builder.proxy(proxy);
+            }
+            Ok(builder.build())
+        };
+
+        Ok(HttpClient {
+            direct: agent(None)?,
+            http_proxy: env_var("HTTP_PROXY").map(|p| agent(Some(p))).transpose()?,
+            https_proxy: env_var("HTTPS_PROXY").map(|p| agent(Some(p))).transpose()?,
+            no_proxy: env_var("NO_PROXY")
+                .map(|hosts| parse_no_proxy(&hosts))
+                .unwrap_or_default(),
+            max_body_size: config.max_body_size,
+            max_redirects: config.max_redirects,
+            semaphore: Semaphore::new(config.max_concurrency),
+        })
+    }
+
+    fn agent(&self, url: &Url) -> &Agent {
+        let proxy = match url.scheme() {
+            "https" => self.https_proxy.as_ref(),
+            _ => self.http_proxy.as_ref(),
+        };
+        match (proxy, url.host_str()) {
+            (Some(agent), Some(host)) if !is_no_proxy(&self.no_proxy, host) => agent,
+            _ => &self.direct,
+        }
+    }
+
+    async fn fetch_html(&self, url: &str) -> Result<HtmlPage> {
+        let response = self.request("GET", url).await?;
+        response.ensure_success()?;
+        if response.is_redirected(url) {
+            println!(
+                "Warning: url `{url}` has been redirected to `{}`",
+                response.url
+            );
+        }
+        Ok(HtmlPage {
+            html: decode_html(&response.body, response.content_type.as_deref()),
+            url: response.url,
+        })
+    }
+
+    async fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>> {
+        let response = self.request("GET", url).await?;
+        response.ensure_success()?;
+        Ok(response.body)
+    }
+
+    pub(crate) async fn head(&self, url: &str) -> Result<(u16, bool)> {
+        let response = self.request("HEAD", url).await?;
+        Ok((response.status, response.is_redirected(url)))
+    }
+
+    async fn request(&self, method: &'static str, url: &str) -> Result<Response> {
+        let agent = self
+            .agent(&Url::parse(url).with_context(|| format!("Invalid url `{url}`"))?)
+            .clone();
+        let max_body_size = self.max_body_size;
+        let max_redirects = self.max_redirects;
+        let url = url.to_owned();
+
+        let _permit = self.semaphore.acquire().await?;
+        // The ureq agent is blocking.
+        tokio::task::spawn_blocking(move || {
+            let response = match agent.request(method, &url).call() {
+                Ok(response) => response,
+                // The 4xx and 5xx responses are checked by the caller.
+                Err(ureq::Error::Status(_, response)) => response,
+                Err(ureq::Error::Transport(err)) if err.kind() == ErrorKind::TooManyRedirects => {
+                    bail!(ZineError::TooManyRedirects(url, max_redirects))
+                }
+                Err(err) => bail!(err),
+            };
+
+            let mut body = Vec::new();
+            let final_url = response.get_url().to_owned();
+            let status = response.status();
+            let content_type = response.header("Content-Type").map(str::to_owned);
+            // The `Content-Length` of the `HEAD` response is the size of the resource.
+            if method != "HEAD" {
+                let content_length = response
+                    .header("Content-Length")
+                    .and_then(|length| length.parse::<u64>().ok());
+                if let Some(length) = content_length.filter(|length| *length > max_body_size) {
+                    bail!("The response body of `{url}` is {length} bytes, exceeds the max size of {max_body_size} bytes");
+                }
+                // The `Content-Length` may be absent or wrong.
+                response
+                    .into_reader()
+                    .take(max_body_size + 1)
+                    .read_to_end(&mut body)?;
+                if body.len() as u64 > max_body_size {
+                    bail!("The response body of `{url}` exceeds the max size of {max_body_size} bytes");
+                }
+            }
+            Ok(Response {
+                url: final_url,
+                status,
+                content_type,
+                body,
+            })
+        })
+        .await?
+    }
+}
+
+impl Response {
+    fn ensure_success(&self) -> Result<()> {
+        // The 3xx response is returned if the redirects are disabled or have no `Location`.
+        if self.status >= 300 {
+            bail!(
+                "`{}` responded with the HTTP status {}",
+                self.url,
+                self.status
+            );
+        }
+        Ok(())
+    }
+
+    fn is_redirected(&self, url: &str) -> bool {
+        // Compare the parsed urls, such as `https://zineland.io` and `https://zineland.io/`.
+        match (Url::parse(url), Url::parse(&self.url)) {
+            (Ok(url), Ok(final_url)) => url != final_url,
+            _ => url != self.url,
+        }
+    }
+}
+
+// Read the environment variable, the lowercase name is also supported.
+fn env_var(name: &str) -> Option<String> {
+    env::var(name)
+        .or_else(|_| env::var(name.to_lowercase()))
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+// Parse the comma-separated hosts of `NO_PROXY`, such as `localhost,.example.com`.
+fn parse_no_proxy(hosts: &str) -> Vec<String> {
+    hosts
+        .split(',')
+        .map(|host| host.trim().trim_start_matches('.').to_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect()
+}
+
+// Whether the host matches the `NO_PROXY` hosts, including the subdomains.
+fn is_no_proxy(no_proxy: &[String], host: &str) -> bool {
+    let host = host.to_lowercase();
+    no_proxy.iter().any(|pattern| {
+        pattern == "*"
+            || host == *pattern
+            || host
+                .strip_suffix(pattern.as_str())
+                .is_some_and(|subdomain| subdomain.ends_with('.'))
+    })
+}
+
+// Decode the HTML into UTF-8. The charset is detected from the BOM, the `Content-Type`
+// header, then the `<meta>` tag, otherwise it is guessed from the content.
+fn decode_html(bytes: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(charset_label)
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .or_else(|| meta_charset(bytes))
+        .unwrap_or_else(|| {
+            if std::str::from_utf8(bytes).is_ok() {
+                UTF_8
+            } else {
+                let mut detector = EncodingDetector::new();
+                detector.feed(bytes, true);
+                detector.guess(None, true)
+            }
+        });
+    // The BOM overrides the detected encoding.
+    let (html, _, _) = encoding.decode(bytes);
+    html.into_owned()
+}
+
+// Extract the charset label from the `Content-Type`, such as `text/html; charset=gbk`.
+fn charset_label(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']))
+    })
+}
+
+// Sniff the charset of the `<meta>` tag in the head of the HTML, such as:
+// - <meta charset="gbk">
+// - <meta http-equiv="Content-Type" content="text/html; charset=gbk">
+fn meta_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(META_SNIFF_SIZE)]).to_lowercase();
+    head.match_indices("charset=").find_map(|(index, pattern)| {
+        let label = head[index + pattern.len()..]
+            .trim_start_matches(['"', '\''])
+            .split(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | ';' | '>' | '/'))
+            .next()?;
+        // The UTF-16 label of `<meta>` means UTF-8 actually.
+        Encoding::for_label(label.as_bytes()).map(Encoding::output_encoding)
+    })
+}
+
+/// Serve a local site to test the HTTP client, return the base url.
+#[cfg(test)]
+pub(crate) async fn serve_test_site() -> String {
+    use std::{convert::Infallible, time::Duration};
+
+    use hyper::{
+        header::{CONTENT_TYPE, LOCATION},
+        service::{make_service_fn, service_fn},
+        Body, Request, Response, Server, StatusCode,
+    };
+
+    async fn route(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+        let path = req.uri().path();
+        let response = if let Some(n) = path.strip_prefix("/redirect/") {
+            match n.parse::<u32>() {
+                Ok(0) => Response::new(Body::from("redirected")),
+                Ok(n) => Response::builder()
+                    .status(StatusCode::FOUND)
+                    .header(LOCATION, format!("/redirect/{}", n - 1))
+                    .body(Body::empty())
+                    .unwrap(),
+                Err(_) => not_found(),
+            }
+        } else {
+            match path {
+                "/page" => {
+                    let (html, _, _) = encoding_rs::GBK
+                        .encode("<html><head><title>中文标题</title></head></html>");
+                    Response::builder()
+                        .header(CONTENT_TYPE, "text/html; charset=gbk")
+                        .body(Body::from(html.into_owned()))
+                        .unwrap()
+                }
+                "/large" => Response::new(Body::from(vec![b'a'; 2048])),
+                "/large-chunked" => {
+                    // No `Content-Length` for the streaming body.
+                    let (mut sender, body) = Body::channel();
+                    tokio::spawn(async move {
+                        for _ in 0..4 {
+                            if sender.send_data(vec![b'a'; 512].into()).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                    Response::new(body)
+                }
+                "/slow" => {
+                    tokio::task::spawn_blocking(|| std::thread::sleep(Duration::from_secs(3)))
+                        .await
+                        .unwrap();
+                    Response::new(Body::from("slow"))
+                }
+                "/error" => Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(Body::empty())
+                    .unwrap(),
+                _ => not_found(),
+            }
+        };
+        Ok(response)
+    }
+
+    fn not_found() -> Response<Body> {
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(route))
+    }));
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    format!("http://{addr}")
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{GBK, SHIFT_JIS};
+    use test_case::test_case;
+
+    use super::{
+        charset_label, decode_html, is_no_proxy, parse_no_proxy, serve_test_site, HttpClient,
+    };
+    use crate::{entity::HttpConfig, error::ZineError};
+
+    fn test_client(config: HttpConfig) -> HttpClient {
+        HttpClient::new(&config).unwrap()
+    }
+
+    #[test_case("text/html; charset=GBK", Some("GBK"))]
+    #[test_case("text/html;charset=\"utf-8\"", Some("utf-8"))]
+    #[test_case("text/html; Charset = gbk ; foo=bar", Some("gbk"))]
+    #[test_case("text/html", None)]
+    fn test_charset_label(content_type: &str, label: Option<&str>) {
+        assert_eq!(charset_label(content_type), label);
+    }
+
+    #[test]
+    fn test_decode_html() {
+        let html = "<html><head><title>中文标题</title></head></html>";
+        assert_eq!(decode_html(html.as_bytes(), None), html);
+
+        let (bytes, _, _) = GBK.encode(html);
+        assert_eq!(decode_html(&bytes, Some("text/html; charset=gbk")), html);
+
+        let html = r#"<html><head><meta charset="shift_jis"><title>日本語のタイトル</title></head></html>"#;
+        let (bytes, _, _) = SHIFT_JIS.encode(html);
+        assert_eq!(decode_html(&bytes, Some("text/html")), html);
+
+        let html = r#"<html><head><meta http-equiv="Content-Type" content="text/html; charset=gbk"><title>中文标题</title></head></html>"#;
+        let (bytes, _, _) = GBK.encode(html);
+        assert_eq!(decode_html(&bytes, None), html);
+
+        // Guess the encoding without any charset declaration.
+        let html = "<html><head><title>这是一个没有声明字符集的中文网页标题</title></head><body>欢迎来到我们的网站，这里有很多有趣的文章。</body></html>";
+        let (bytes, _, _) = GBK.encode(html);
+        assert_eq!(decode_html(&bytes, None), html);
+    }
+
+    #[test_case("example.com", true)]
+    #[test_case("blog.example.com", true)]
+    #[test_case("LOCALHOST", true)]
+    #[test_case("notexample.com", false)]
+    #[test_case("zineland.io", false)]
+    fn test_no_proxy(host: &str, expected: bool) {
+        let no_proxy = parse_no_proxy(" localhost, .example.com,,");
+        assert_eq!(is_no_proxy(&no_proxy, host), expected);
+        assert!(is_no_proxy(&parse_no_proxy("*"), host));
+    }
+
+    #[test_case(2, 2, true; "within the limit")]
+    #[test_case(3, 2, false; "over the limit")]
+    #[test_case(1, 0, false; "redirects disabled")]
+    #[tokio::test]
+    async fn test_redirects(redirects: u32, max_redirects: u32, success: bool) {
+        let base_url = serve_test_site().await;
+        let client = test_client(HttpConfig {
+            max_redirects,
+            ..Default::default()
+        });
+        let url = format!("{base_url}/redirect/{redirects}");
+        let result = client.fetch_bytes(&url).await;
+        if success {
+            assert_eq!(result.unwrap(), b"redirected");
+        } else {
+            assert!(result.is_err());
+        }
+
+        let head = client.head(&url).await;
+        match (max_redirects, success) {
+            (_, true) => assert_eq!(head.unwrap(), (200, true)),
+            // The 3xx response is returned if the redirects are disabled.
+            (0, _) => assert_eq!(head.unwrap(), (302, false)),
+            _ => {
+                let err = head.unwrap_err().downcast::<ZineError>().unwrap();
+                assert!(matches!(err, ZineError::TooManyRedirects(_, 2)));
+            }
+        }
+    }
+
+    #[test_case("/large", "is 2048 bytes, exceeds the max size of 1024 bytes"; "content length")]
+    #[test_case("/large-chunked", "exceeds the max size of 1024 bytes"; "streaming body")]
+    #[tokio::test]
+    async fn test_max_body_size(path: &str, message: &str) {
+        let base_url = serve_test_site().await;
+        let client = test_client(HttpConfig {
+            max_body_size: 1024,
+            ..Default::default()
+        });
+        let url = format!("{base_url}{path}");
+        let err = client.fetch_bytes(&url).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("The response body of `{url}` {message}")
+        );
+        // The `HEAD` request has no body to limit.
+        assert_eq!(client.head(&url).await.unwrap(), (200, false));
+
+        let client = test_client(HttpConfig {
+            max_body_size: 2048,
+            ..Default::default()
+        });
+        assert_eq!(client.fetch_bytes(&url).await.unwrap().len(), 2048);
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout() {
+        let base_url = serve_test_site().await;
+        let client = test_client(HttpConfig {
+            read_timeout: 1,
+            ..Default::default()
+        });
+        assert!(client
+            .fetch_bytes(&format!("{base_url}/slow"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_html() {
+        let base_url = serve_test_site().await;
+        let client = test_client(HttpConfig::default());
+        let url = format!("{base_url}/page");
+        let page = client.fetch_html(&url).await.unwrap();
+        assert_eq!(page.url, url);
+        assert_eq!(
+            page.html,
+            "<html><head><title>中文标题</title></head></html>"
+        );
+
+        for path in ["/missing", "/error"] {
+            assert!(client
+                .fetch_html(&format!("{base_url}{path}"))
+                .await
+                .is_err());
+        }
+    }
+}
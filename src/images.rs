@@ -0,0 +1,365 @@
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use image::{
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, png::PngEncoder},
+    imageops::FilterType,
+    DynamicImage,
+};
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use serde::Serialize;
+
+use crate::{
+    entity::{ImageConfig, ImageFormat},
+    helpers,
+};
+
+/// The directory (relative to the cache directory) of processed images.
+static CACHE_DIR: &str = "images";
+/// The directory (relative to the dest directory) of processed images.
+static OUTPUT_DIR: &str = "static/_images";
+/// The directory (relative to both the source and dest directory) of downloaded preview images.
+pub(crate) static PREVIEWS_DIR: &str = "static/previews";
+
+static PIPELINE: OnceCell<RwLock<ImagePipeline>> = OnceCell::new();
+
+/// Initialize the image pipeline, must be called before rendering.
+///
+/// The in-memory processed images are reset, but the cached variants
+/// on disk are kept.
+pub fn init(source: &Path, dest: &Path, config: &ImageConfig) {
+    let pipeline = PIPELINE.get_or_init(|| RwLock::new(ImagePipeline::new(source, dest, config)));
+    *pipeline.write() = ImagePipeline::new(source, dest, config);
+}
+
+/// Process the image of `url` into a [`ResponsiveImage`].
+///
+/// Return `None` if the responsive pipeline is disabled, the url isn't a local image
+/// or the image format is unsupported.
+pub fn process(url: &str) -> Option<ResponsiveImage> {
+    PIPELINE.get()?.read().process(url)
+}
+
+/// Whether to download the url preview images.
+pub fn is_preview_download_enabled() -> bool {
+    PIPELINE
+        .get()
+        .is_some_and(|pipeline| pipeline.read().config.download_previews)
+}
+
+/// Whether the downloaded preview image of `url`, such as `/static/previews/{hash}.png`,
+/// exists in the source directory.
+pub fn has_preview_image(url: &str) -> bool {
+    PIPELINE.get().is_some_and(|pipeline| {
+        url.strip_prefix('/')
+            .is_some_and(|path| pipeline.read().source.join(path).is_file())
+    })
+}
+
+/// Save the downloaded preview image with the content-hashed file name,
+/// return the root path url of the saved image.
+pub fn save_preview_image(bytes: &[u8]) -> Result<String> {
+    let pipeline = PIPELINE
+        .get()
+        .context("The image pipeline isn't initialized")?
+        .read();
+    let format = image::guess_format(bytes).context("The preview image has unknown format")?;
+    let file_name = format!(
+        "{}.{}",
+        helpers::content_hash(bytes),
+        format.extensions_str().first().copied().unwrap_or_default()
+    );
+    // Save into the source directory to keep it between builds, and the dest directory
+    // whose static assets have been copied before rendering.
+    for dir in [&pipeline.source, &pipeline.dest] {
+        let dir = dir.join(PREVIEWS_DIR);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(&file_name), bytes)?;
+    }
+    Ok(format!("/{PREVIEWS_DIR}/{file_name}"))
+}
+
+/// A processed image with its resized variants.
+#[derive(Clone, Debug, Serialize)]
+pub struct ResponsiveImage {
+    /// The intrinsic width of the original image.
+    pub width: u32,
+    /// The intrinsic height of the original image.
+    pub height: u32,
+    /// The `srcset` of the variants in original format.
+    pub srcset: String,
+    /// The default `sizes` attribute.
+    pub sizes: String,
+    /// The variants in extra formats, such as webp and avif.
+    pub sources: Vec<ImageSource>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ImageSource {
+    #[serde(rename = "type")]
+    pub mime_type: &'static str,
+    pub srcset: String,
+}
+
+impl From<ImageFormat> for image::ImageFormat {
+    fn from(format: ImageFormat) -> Self {
+        match format {
+            ImageFormat::Webp => image::ImageFormat::WebP,
+            ImageFormat::Avif => image::ImageFormat::Avif,
+        }
+    }
+}
+
+struct ImagePipeline {
+    source: PathBuf,
+    dest: PathBuf,
+    config: ImageConfig,
+    // The processed images, keyed by url.
+    images: DashMap<String, Arc<OnceCell<Option<ResponsiveImage>>>>,
+}
+
+impl ImagePipeline {
+    fn new(source: &Path, dest: &Path, config: &ImageConfig) -> Self {
+        ImagePipeline {
+            source: source.to_path_buf(),
+            dest: dest.to_path_buf(),
+            config: config.clone(),
+            images: DashMap::default(),
+        }
+    }
+
+    fn cache_dir(&self) -> PathBuf {
+        self.source.join(crate::ZINE_CACHE_DIR).join(CACHE_DIR)
+    }
+
+    // Resolve the local file path of a root path url, such as `/static/cover.png`.
+    fn resolve_path(&self, url: &str) -> Option<PathBuf> {
+        let path = url.strip_prefix('/')?;
+        // Protocol-relative url, such as `//example.com/cover.png`.
+        if path.starts_with('/') {
+            return None;
+        }
+        let file = self.source.join(path);
+        if file.is_file() {
+            return Some(file);
+        }
+        // The co-located assets of articles have been copied into the dest directory.
+        let file = self.dest.join(path);
+        file.is_file().then_some(file)
+    }
+
+    fn process(&self, url: &str) -> Option<ResponsiveImage> {
+        if !self.config.responsive {
+            return None;
+        }
+
+        let path = self.resolve_path(url)?;
+        // Other threads rendering the same image wait for the first one to finish.
+        let cell = Arc::clone(&self.images.entry(url.to_owned()).or_default());
+        cell.get_or_init(|| match self.process_image(&path) {
+            Ok(image) => image,
+            Err(err) => {
+                println!("Warning: failed to process image `{url}`: {err}");
+                None
+            }
+        })
+        .clone()
+    }
+
+    fn process_image(&self, path: &Path) -> Result<Option<ResponsiveImage>> {
+        let original_format = match image::ImageFormat::from_path(path) {
+            Ok(
+                format @ (image::ImageFormat::Png
+                | image::ImageFormat::Jpeg
+                | image::ImageFormat::WebP),
+            ) => format,
+            // Other formats (such as svg and animated gif) are kept as is.
+            _ => return Ok(None),
+        };
+
+        let hash = helpers::content_hash(&fs::read(path)?);
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+        let (width, height) = image::image_dimensions(path)?;
+
+        let mut widths = self
+            .config
+            .widths
+            .iter()
+            .copied()
+            .filter(|w| *w < width)
+            .collect::<Vec<_>>();
+        widths.push(width);
+        widths.sort_unstable();
+        widths.dedup();
+
+        fs::create_dir_all(self.cache_dir())?;
+        fs::create_dir_all(self.dest.join(OUTPUT_DIR))?;
+
+        // Decode lazily, only if some variants are missing in the cache.
+        let mut decoded: Option<DynamicImage> = None;
+        let mut srcset = |format: image::ImageFormat| -> Result<String> {
+            let mut candidates = Vec::with_capacity(widths.len());
+            for &width in &widths {
+                // The quality is a part of the name, the cached variants
+                // are reprocessed once the quality changed.
+                let file_name = format!(
+                    "{stem}-{hash}-{width}w-q{}.{}",
+                    self.config.quality,
+                    format.extensions_str().first().copied().unwrap_or_default()
+                );
+                let cached = self.cache_dir().join(&file_name);
+                if !cached.exists() {
+                    let image = match decoded.as_ref() {
+                        Some(image) => image,
+                        None => decoded.insert(image::open(path)?),
+                    };
+                    encode(image, width, format, self.config.quality, &cached)?;
+                }
+
+                let output = self.dest.join(OUTPUT_DIR).join(&file_name);
+                if !output.exists() {
+                    fs::copy(&cached, output)?;
+                }
+                candidates.push(format!("/{OUTPUT_DIR}/{file_name} {width}w"));
+            }
+            Ok(candidates.join(", "))
+        };
+
+        let mut sources = Vec::with_capacity(self.config.formats.len());
+        for format in &self.config.formats {
+            let format = image::ImageFormat::from(*format);
+            if format == original_format {
+                continue;
+            }
+            sources.push(ImageSource {
+                mime_type: format.to_mime_type(),
+                srcset: srcset(format)?,
+            });
+        }
+
+        Ok(Some(ResponsiveImage {
+            width,
+            height,
+            srcset: srcset(original_format)?,
+            sizes: self.config.sizes.clone(),
+            sources,
+        }))
+    }
+}
+
+// Resize `image` to `width` and encode it into `path` with `format`.
+fn encode(
+    image: &DynamicImage,
+    width: u32,
+    format: image::ImageFormat,
+    quality: u8,
+    path: &Path,
+) -> Result<()> {
+    let image = if width < image.width() {
+        Cow::Owned(image.resize(width, u32::MAX, FilterType::Lanczos3))
+    } else {
+        Cow::Borrowed(image)
+    };
+
+    // Write to a temporary file first, avoid leaving a broken file in the cache.
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    match format {
+        image::ImageFormat::Jpeg => DynamicImage::from(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut writer, quality))?,
+        image::ImageFormat::Png => image.write_with_encoder(PngEncoder::new(&mut writer))?,
+        image::ImageFormat::WebP => {
+            // The image crate only ships a lossless WebP encoder.
+            let rgba = image.to_rgba8();
+            let webp =
+                webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height()).encode(quality as f32);
+            writer.write_all(&webp)?;
+        }
+        image::ImageFormat::Avif => image
+            .write_with_encoder(AvifEncoder::new_with_speed_quality(&mut writer, 8, quality))?,
+        _ => unreachable!("Unsupported image format: {:?}", format),
+    }
+    writer.flush()?;
+    drop(writer);
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageFormat as RawFormat};
+
+    use super::ImagePipeline;
+    use crate::entity::{ImageConfig, ImageFormat};
+
+    fn config() -> ImageConfig {
+        ImageConfig {
+            responsive: true,
+            widths: vec![4, 8, 32],
+            formats: vec![ImageFormat::Webp],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_process_image() {
+        let source = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        let (source, dest) = (source.path(), dest.path());
+        std::fs::create_dir_all(source.join("static")).unwrap();
+        DynamicImage::new_rgb8(16, 10)
+            .save_with_format(source.join("static/cover.png"), RawFormat::Png)
+            .unwrap();
+
+        let pipeline = ImagePipeline::new(source, dest, &config());
+        let image = pipeline.process("/static/cover.png").unwrap();
+        assert_eq!((image.width, image.height), (16, 10));
+        assert_eq!(image.sources.len(), 1);
+        assert_eq!(image.sources[0].mime_type, "image/webp");
+        let webp = image.sources[0].srcset.split(' ').next().unwrap();
+        let webp = std::fs::read(dest.join(webp.trim_start_matches('/'))).unwrap();
+        // Lossy (VP8) rather than lossless (VP8L) encoding.
+        assert_eq!(&webp[12..16], b"VP8 ");
+
+        let candidates = image.srcset.split(", ").collect::<Vec<_>>();
+        assert_eq!(candidates.len(), 3);
+        assert!(candidates[0].ends_with("-4w-q80.png 4w"));
+        assert!(candidates[2].ends_with("-16w-q80.png 16w"));
+        for candidate in candidates {
+            let url = candidate.split(' ').next().unwrap();
+            assert!(dest.join(url.trim_start_matches('/')).exists());
+        }
+
+        assert!(pipeline.process("/static/missing.png").is_none());
+        assert!(pipeline.process("https://example.com/cover.png").is_none());
+        assert!(pipeline.process("//example.com/cover.png").is_none());
+
+        // Changing the quality never reuses the cached variants.
+        let pipeline = ImagePipeline::new(
+            source,
+            dest,
+            &ImageConfig {
+                quality: 50,
+                ..config()
+            },
+        );
+        let image = pipeline.process("/static/cover.png").unwrap();
+        assert!(image.srcset.starts_with("/static/_images/cover-"));
+        assert!(image.srcset.contains("-4w-q50.png 4w"));
+
+        let disabled = ImagePipeline::new(source, dest, &ImageConfig::default());
+        assert!(disabled.process("/static/cover.png").is_none());
+        assert_eq!(ImageConfig::default().formats, vec![ImageFormat::Webp]);
+    }
+}
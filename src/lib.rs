@@ -8,22 +8,30 @@ use walkdir::WalkDir;
 
 pub mod build;
 mod code_blocks;
+mod compress;
 mod data;
 mod engine;
 mod entity;
 mod error;
 mod feed;
+mod fingerprint;
 pub mod helpers;
 mod html;
+mod http;
 mod i18n;
+mod images;
 pub mod lint;
 mod locales;
 mod markdown;
+mod minify;
 pub mod new;
+pub mod preview;
 pub mod serve;
+mod themes;
 
 pub use self::engine::ZineEngine;
 pub use self::entity::Entity;
+pub use self::markdown::list_highlight_themes;
 
 /// The convention name of zine config file.
 pub static ZINE_FILE: &str = "zine.toml";
@@ -31,6 +39,14 @@ pub static ZINE_FILE: &str = "zine.toml";
 pub static ZINE_CONTENT_DIR: &str = "content";
 /// The convention name of introduction file for zine issue.
 pub static ZINE_INTRO_FILE: &str = "intro.md";
+/// The convention name of zine templates directory, whose templates override
+/// the builtin templates of the same name.
+pub static ZINE_TEMPLATES_DIR: &str = "templates";
+/// The convention name of zine highlight directory, whose syntaxes (`.sublime-syntax`)
+/// and themes (`.tmTheme`) are merged into the builtin ones.
+pub static ZINE_HIGHLIGHT_DIR: &str = "highlight";
+/// The convention name of zine cache directory, which keeps the build artifacts between builds.
+pub static ZINE_CACHE_DIR: &str = ".zine-cache";
 pub static ZINE_BANNER: &str = r"
 
 ███████╗██╗███╗   ██╗███████╗
@@ -2,14 +2,14 @@ use std::{collections::HashMap, path::Path};
 
 use anyhow::Result;
 use futures::future::try_join_all;
-use hyper::{Client, Request};
-use hyper_tls::HttpsConnector;
 
-use crate::data;
+use crate::{data, entity::Zine, error::ZineError, http};
 
 /// Lint the zine project.
 /// Return true if lint success.
 pub async fn lint_zine_project<P: AsRef<Path>>(source: P) -> Result<bool> {
+    http::init(&Zine::parse_from_toml(&source)?.http_config)?;
+
     let tasks = {
         data::load(source);
         let guard = data::read();
@@ -53,21 +53,29 @@ pub async fn lint_zine_project<P: AsRef<Path>>(source: P) -> Result<bool> {
 }
 
 async fn check_url(url: String) -> Result<(String, UrlCondition)> {
-    let client = Client::builder().build::<_, hyper::Body>(HttpsConnector::new());
-    let req = Request::head(url.as_str()).body(hyper::Body::empty())?;
-    let resp = client.request(req).await?;
+    let condition = url_condition(http::head(&url).await)?;
+    Ok((url, condition))
+}
 
-    let status = resp.status();
-    let condition = if status.as_u16() == 404 {
+fn url_condition(head: Result<(u16, bool)>) -> Result<UrlCondition> {
+    let (status, redirected) = match head {
+        Ok(head) => head,
+        Err(err) => match err.downcast::<ZineError>() {
+            Ok(ZineError::TooManyRedirects(..)) => return Ok(UrlCondition::Redirected),
+            Ok(err) => return Err(err.into()),
+            Err(err) => return Err(err),
+        },
+    };
+    let condition = if status == 404 {
         UrlCondition::NotFound
-    } else if status.is_redirection() {
+    } else if redirected || (300..400).contains(&status) {
         UrlCondition::Redirected
-    } else if status.is_server_error() {
+    } else if status >= 500 {
         UrlCondition::ServerError
     } else {
         UrlCondition::Normal
     };
-    Ok((url, condition))
+    Ok(condition)
 }
 
 #[derive(Debug, Hash, PartialEq, Eq)]
@@ -77,3 +85,32 @@ enum UrlCondition {
     Redirected,
     ServerError,
 }
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{url_condition, UrlCondition};
+    use crate::{
+        entity::HttpConfig,
+        http::{serve_test_site, HttpClient},
+    };
+
+    #[test_case("/page", 5, UrlCondition::Normal; "normal")]
+    #[test_case("/missing", 5, UrlCondition::NotFound; "not found")]
+    #[test_case("/redirect/1", 5, UrlCondition::Redirected; "redirected")]
+    #[test_case("/redirect/3", 2, UrlCondition::Redirected; "too many redirects")]
+    #[test_case("/redirect/1", 0, UrlCondition::Redirected; "redirects disabled")]
+    #[test_case("/error", 5, UrlCondition::ServerError; "server error")]
+    #[tokio::test]
+    async fn test_url_condition(path: &str, max_redirects: u32, expected: UrlCondition) {
+        let base_url = serve_test_site().await;
+        let client = HttpClient::new(&HttpConfig {
+            max_redirects,
+            ..Default::default()
+        })
+        .unwrap();
+        let head = client.head(&format!("{base_url}{path}")).await;
+        assert_eq!(url_condition(head).unwrap(), expected);
+    }
+}
@@ -16,32 +16,52 @@ pub struct FluentLoader {
 }
 
 impl FluentLoader {
-    pub fn new(source: &Path, mut locale: &str) -> Self {
-        let resource = match locale {
-            "en" => FluentResource::try_new(FLUENT_EN.to_owned()),
-            "zh" => FluentResource::try_new(FLUENT_ZH_CN.to_owned()),
-            _ => {
-                // Not a buitlin locale, load the user translation resource.
-                let file = format!("locales/{}.ftl", locale);
-                let path = source.join(&file);
-                if path.exists() {
-                    let translation = fs::read_to_string(path)
-                        .unwrap_or_else(|err| panic!("{file} read failed: {}", err));
-                    FluentResource::try_new(translation)
-                } else {
-                    println!("Warning: `{file}` does not exist, please add your translation to this file.");
-                    println!("fallback to default `en` locale.");
+    /// The translation files of `theme_dir` (the theme package directory)
+    /// and `source` are loaded over the builtin translation in order.
+    pub fn new(source: &Path, theme_dir: Option<&Path>, mut locale: &str) -> Self {
+        let file = format!("locales/{}.ftl", locale);
+        let translations = theme_dir
+            .into_iter()
+            .chain([source])
+            .map(|dir| dir.join(&file))
+            .filter(|path| path.exists())
+            .map(|path| {
+                fs::read_to_string(path).unwrap_or_else(|err| panic!("{file} read failed: {}", err))
+            })
+            .collect::<Vec<_>>();
 
-                    locale = "en";
-                    FluentResource::try_new(FLUENT_EN.to_owned())
-                }
+        let builtin = match locale {
+            "en" => Some(FLUENT_EN),
+            "zh" => Some(FLUENT_ZH_CN),
+            _ => None,
+        };
+        let builtin = match builtin {
+            Some(builtin) => builtin,
+            // Not a buitlin locale, and no user translation resource.
+            None if translations.is_empty() => {
+                println!(
+                    "Warning: `{file}` does not exist, please add your translation to this file."
+                );
+                println!("fallback to default `en` locale.");
+
+                locale = "en";
+                FLUENT_EN
             }
-        }
-        .expect("Load translation failed.");
+            // Not a buitlin locale, fallback the missing messages to `en`.
+            None => FLUENT_EN,
+        };
 
         let lang_id = locale.parse().expect("Invalid locale string.");
         let mut bundle = FluentBundle::new_concurrent(vec![lang_id]);
-        bundle.add_resource(resource).unwrap();
+        bundle
+            .add_resource(
+                FluentResource::try_new(builtin.to_owned()).expect("Load translation failed."),
+            )
+            .unwrap();
+        for translation in translations {
+            let resource = FluentResource::try_new(translation).expect("Load translation failed.");
+            bundle.add_resource_overriding(resource);
+        }
         FluentLoader { bundle }
     }
 }
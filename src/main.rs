@@ -1,7 +1,10 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use zine::build::watch_build;
 use zine::new::{new_zine_issue, new_zine_project};
+use zine::preview::{self, fetch_previews, refresh_previews};
 use zine::serve::run_serve;
 use zine::{lint, Mode};
 
@@ -24,6 +27,12 @@ enum Commands {
         /// Enable watching.
         #[arg(short, long)]
         watch: bool,
+        /// Write precompressed gzip and brotli files alongside the build output.
+        #[arg(long)]
+        compress: bool,
+        /// Never fetch the url previews, render the cached ones only.
+        #[arg(long)]
+        offline: bool,
     },
     /// Serve the Zine site.
     Serve {
@@ -32,6 +41,12 @@ enum Commands {
         /// The listen port.
         #[arg(short, default_value_t = 3000)]
         port: u16,
+        /// Write precompressed gzip and brotli files, then serve them by `Accept-Encoding`.
+        #[arg(long)]
+        compress: bool,
+        /// Never fetch the url previews, render the cached ones only.
+        #[arg(long)]
+        offline: bool,
     },
     /// New a Zine project.
     New {
@@ -49,10 +64,41 @@ enum Commands {
         #[arg(long)]
         ci: bool,
     },
+    /// Manage the url previews cached in `zine-data.json`.
+    Preview {
+        #[command(subcommand)]
+        command: PreviewCommands,
+    },
+    /// List the available highlight themes, including the project's themes.
+    HighlightThemes {
+        /// The source directory of zine site.
+        source: Option<String>,
+    },
     /// Prints the app version.
     Version,
 }
 
+#[derive(Debug, Subcommand)]
+enum PreviewCommands {
+    /// Fetch the uncached url previews of the markdown files.
+    Fetch {
+        /// The source directory of zine site.
+        #[arg(short, long, default_value = ".")]
+        source: String,
+    },
+    /// Refetch the cached url previews.
+    Refresh {
+        /// The url to refresh. Default to refresh all the cached previews.
+        url: Option<String>,
+        /// Only refresh the previews fetched before the duration, such as `30d`.
+        #[arg(long, value_parser = preview::parse_duration)]
+        older_than: Option<Duration>,
+        /// The source directory of zine site.
+        #[arg(short, long, default_value = ".")]
+        source: String,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     match Cli::parse().command {
@@ -60,15 +106,36 @@ async fn main() -> Result<()> {
             source,
             dest,
             watch,
+            compress,
+            offline,
         } => {
             zine::set_current_mode(Mode::Build);
             let dest = dest.unwrap_or_else(|| "build".into());
-            watch_build(&source.unwrap_or_else(|| ".".into()), &dest, watch, None).await?;
+            watch_build(
+                &source.unwrap_or_else(|| ".".into()),
+                &dest,
+                watch,
+                compress,
+                offline,
+                None,
+            )
+            .await?;
             println!("Build success! The build directory is `{}`.", dest);
         }
-        Commands::Serve { source, port } => {
+        Commands::Serve {
+            source,
+            port,
+            compress,
+            offline,
+        } => {
             zine::set_current_mode(Mode::Serve);
-            run_serve(source.unwrap_or_else(|| ".".into()), port).await?;
+            run_serve(
+                source.unwrap_or_else(|| ".".into()),
+                port,
+                compress,
+                offline,
+            )
+            .await?;
         }
         Commands::New { name, issue } => {
             if issue {
@@ -83,6 +150,17 @@ async fn main() -> Result<()> {
                 std::process::exit(1);
             }
         }
+        Commands::Preview { command } => match command {
+            PreviewCommands::Fetch { source } => fetch_previews(source).await?,
+            PreviewCommands::Refresh {
+                url,
+                older_than,
+                source,
+            } => refresh_previews(source, older_than, url).await?,
+        },
+        Commands::HighlightThemes { source } => {
+            zine::list_highlight_themes(source.unwrap_or_else(|| ".".into()))?;
+        }
         Commands::Version => {
             let version =
                 option_env!("CARGO_PKG_VERSION").unwrap_or("(Unknown Cargo package version)");
@@ -0,0 +1,144 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use percent_encoding::percent_decode_str;
+
+/// The co-located assets of a markdown file, such as the images
+/// and attachments placed next to the article's markdown file.
+#[derive(Debug, Clone)]
+pub struct ColocatedAssets {
+    // The directory of the markdown file.
+    source_dir: PathBuf,
+    // The output directory of the rendered page.
+    dest_dir: PathBuf,
+    // The url path of the rendered page, such as `/issue-1/first-article`.
+    base_url: String,
+}
+
+impl ColocatedAssets {
+    pub fn new(source_dir: &Path, dest_dir: &Path, base_url: &str) -> Self {
+        ColocatedAssets {
+            source_dir: source_dir.to_path_buf(),
+            dest_dir: dest_dir.to_path_buf(),
+            base_url: base_url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    /// Copy the asset referenced by the relative `url` into the output directory,
+    /// then return the rewritten root path url of the asset.
+    ///
+    /// Return `None` if the `url` doesn't reference a co-located asset.
+    pub fn resolve(&self, url: &str) -> Option<String> {
+        if !is_relative_url(url) {
+            return None;
+        }
+
+        // Strip the query and fragment part, then decode the escaped
+        // characters, such as `my%20file.png`.
+        let path = url.split(['?', '#']).next().unwrap_or_default();
+        let path = percent_decode_str(path).decode_utf8().ok()?;
+        let relative_path = Path::new(path.as_ref());
+        // Only assets inside the markdown directory are allowed,
+        // otherwise they would be copied outside of the page directory.
+        if !relative_path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+            || relative_path.extension().is_some_and(|ext| ext == "md")
+        {
+            return None;
+        }
+
+        let file = self.source_dir.join(relative_path);
+        if !file.is_file() {
+            return None;
+        }
+
+        let to = self.dest_dir.join(relative_path);
+        let copied = to
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::copy(&file, &to));
+        if let Err(err) = copied {
+            println!(
+                "Warning: failed to copy asset `{}`: {}",
+                file.display(),
+                err
+            );
+            return None;
+        }
+
+        Some(format!(
+            "{}/{}",
+            self.base_url,
+            url.trim_start_matches("./")
+        ))
+    }
+}
+
+// Whether the url is relative to the current markdown file,
+// such as `images/cover.png` or `./report.pdf`.
+fn is_relative_url(url: &str) -> bool {
+    let has_scheme = url
+        .split_once(':')
+        .is_some_and(|(scheme, _)| !scheme.contains('/'));
+    !url.is_empty() && !has_scheme && !url.starts_with(['/', '#', '?'])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use test_case::test_case;
+
+    use super::{is_relative_url, ColocatedAssets};
+
+    #[test_case("images/a.png", true; "nested path")]
+    #[test_case("./a.png", true; "current dir")]
+    #[test_case("../a.png", true; "parent dir")]
+    #[test_case("/static/a.png", false; "root path")]
+    #[test_case("//example.com/a.png", false; "protocol relative")]
+    #[test_case("https://example.com/a.png", false; "absolute url")]
+    #[test_case("mailto:zine@example.com", false; "mailto")]
+    #[test_case("#heading", false; "anchor")]
+    #[test_case("", false; "empty")]
+    fn test_is_relative_url(url: &str, expected: bool) {
+        assert_eq!(is_relative_url(url), expected);
+    }
+
+    #[test]
+    fn test_resolve_assets() {
+        let source = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        let (source, dest) = (source.path(), dest.path());
+        fs::create_dir_all(source.join("images")).unwrap();
+        fs::write(source.join("images/a.png"), "png").unwrap();
+        fs::write(source.join("report.pdf"), "pdf").unwrap();
+        fs::write(source.join("my file.png"), "png").unwrap();
+        fs::write(source.join("2-second.md"), "md").unwrap();
+
+        let assets = ColocatedAssets::new(source, dest, "/issue-1/first/");
+        assert_eq!(
+            assets.resolve("images/a.png").as_deref(),
+            Some("/issue-1/first/images/a.png")
+        );
+        assert!(dest.join("images/a.png").exists());
+        assert_eq!(
+            assets.resolve("./report.pdf?v=1").as_deref(),
+            Some("/issue-1/first/report.pdf?v=1")
+        );
+        assert!(dest.join("report.pdf").exists());
+        assert_eq!(
+            assets.resolve("my%20file.png").as_deref(),
+            Some("/issue-1/first/my%20file.png")
+        );
+        assert!(dest.join("my file.png").exists());
+
+        assert_eq!(assets.resolve("missing.png"), None);
+        assert_eq!(assets.resolve("2-second.md"), None);
+        assert_eq!(assets.resolve("../report.pdf"), None);
+        assert_eq!(assets.resolve("%2e%2e/report.pdf"), None);
+        assert_eq!(assets.resolve("/static/a.png"), None);
+    }
+}
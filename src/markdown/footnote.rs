@@ -0,0 +1,289 @@
+use std::{collections::HashMap, fmt::Write};
+
+use pulldown_cmark::{escape::escape_html, html, Event};
+
+// The marker wraps the reference index in the rendered html, which is replaced
+// after all the definitions are collected.
+const MARKER: char = '\u{1}';
+
+/// The footnotes collected while rendering markdown.
+///
+/// The references are numbered in order of their first appearance,
+/// each definition links back to its references.
+#[derive(Default)]
+pub struct Footnotes<'a> {
+    // The labels of the references in order.
+    references: Vec<String>,
+    definitions: HashMap<String, Vec<Event<'a>>>,
+    // The label and events of the definition being collected.
+    collecting: Option<(String, Vec<Event<'a>>)>,
+}
+
+impl<'a> Footnotes<'a> {
+    /// Record a reference, return the placeholder event.
+    pub fn reference(&mut self, label: &str) -> Event<'static> {
+        let index = self.references.len();
+        self.references.push(label.to_owned());
+        Event::Html(format!("{MARKER}{index}{MARKER}").into())
+    }
+
+    pub fn start_definition(&mut self, label: &str) {
+        self.collecting = Some((label.to_owned(), Vec::new()));
+    }
+
+    pub fn end_definition(&mut self) {
+        if let Some((label, events)) = self.collecting.take() {
+            self.definitions.insert(label, events);
+        }
+    }
+
+    /// Collect the event if it belongs to a definition, otherwise return it.
+    pub fn collect(&mut self, event: Event<'a>) -> Option<Event<'a>> {
+        match self.collecting.as_mut() {
+            Some((_, events)) => {
+                events.push(event);
+                None
+            }
+            None => Some(event),
+        }
+    }
+
+    /// Replace the reference placeholders of `html` and append the footnotes.
+    ///
+    /// If `sidenotes` enabled, the definitions are also rendered next to their
+    /// first references, which are displayed in the margin on wide screens.
+    pub fn render(mut self, html: String, sidenotes: bool) -> String {
+        if self.references.is_empty() {
+            return html;
+        }
+
+        // The numbered labels and reference counts.
+        let mut numbered: Vec<(&str, usize)> = Vec::new();
+        let mut definitions = HashMap::new();
+        for (label, events) in self.definitions.drain() {
+            let mut definition = String::new();
+            html::push_html(&mut definition, events.into_iter());
+            definitions.insert(label, definition);
+        }
+
+        let mut output = String::with_capacity(html.len());
+        for (i, part) in html.split(MARKER).enumerate() {
+            // The odd parts are the reference indexes.
+            let label = match part.parse::<usize>() {
+                Ok(index) if i % 2 == 1 => &self.references[index],
+                _ => {
+                    output.push_str(part);
+                    continue;
+                }
+            };
+            let Some(definition) = definitions.get(label) else {
+                println!("Warning: footnote `{label}` is not defined");
+                push_undefined(&mut output, label);
+                continue;
+            };
+
+            let number = number_of(&mut numbered, label);
+            numbered[number - 1].1 += 1;
+            let count = numbered[number - 1].1;
+            let _ = write!(
+                output,
+                r##"<sup class="footnote-ref" id="{}"><a href="#fn-{number}">{number}</a></sup>"##,
+                reference_id(number, count),
+            );
+            if sidenotes && count == 1 {
+                let definition =
+                    resolve_nested(definition, &self.references, &definitions, &mut numbered);
+                let _ = write!(
+                    output,
+                    r#"<span class="sidenote" role="note"><span class="sidenote-number">{number}</span> {}</span>"#,
+                    inline_definition(&definition),
+                );
+            }
+        }
+
+        if numbered.is_empty() {
+            return output;
+        }
+        let class = if sidenotes {
+            "footnotes footnotes-with-sidenotes"
+        } else {
+            "footnotes"
+        };
+        let _ = write!(output, r#"<section class="{class}"><ol>"#);
+        // The nested references may number more footnotes while rendering.
+        let mut index = 0;
+        while index < numbered.len() {
+            let (label, count) = numbered[index];
+            index += 1;
+            let number = index;
+            let backrefs = (1..=count)
+                .map(|n| {
+                    let sup = if count > 1 {
+                        format!("<sup>{n}</sup>")
+                    } else {
+                        String::new()
+                    };
+                    format!(
+                        r##" <a class="footnote-backref" href="#{}" aria-label="Back to reference {number}">↩{sup}</a>"##,
+                        reference_id(number, n)
+                    )
+                })
+                .collect::<String>();
+            let definition = resolve_nested(
+                &definitions[label],
+                &self.references,
+                &definitions,
+                &mut numbered,
+            );
+            let definition = definition.trim_end();
+            // Put the back links at the end of the last paragraph.
+            let definition = match definition.strip_suffix("</p>") {
+                Some(definition) => format!("{definition}{backrefs}</p>"),
+                None => format!("{definition}{backrefs}"),
+            };
+            let _ = write!(output, r#"<li id="fn-{number}">{definition}</li>"#);
+        }
+        output.push_str("</ol></section>\n");
+        output
+    }
+}
+
+// The number of the footnote `label`, which is numbered if it isn't yet.
+fn number_of<'r>(numbered: &mut Vec<(&'r str, usize)>, label: &'r str) -> usize {
+    match numbered.iter().position(|(l, _)| *l == label) {
+        Some(position) => position + 1,
+        None => {
+            numbered.push((label, 0));
+            numbered.len()
+        }
+    }
+}
+
+// Replace the reference placeholders nested in a definition. The nested
+// references link to the footnotes, but have no back links.
+fn resolve_nested<'r>(
+    definition: &str,
+    references: &'r [String],
+    definitions: &HashMap<String, String>,
+    numbered: &mut Vec<(&'r str, usize)>,
+) -> String {
+    let mut output = String::with_capacity(definition.len());
+    for (i, part) in definition.split(MARKER).enumerate() {
+        match part.parse::<usize>() {
+            Ok(index) if i % 2 == 1 => {
+                let label = &references[index];
+                if definitions.contains_key(label) {
+                    let number = number_of(numbered, label);
+                    let _ = write!(
+                        output,
+                        r##"<sup class="footnote-ref"><a href="#fn-{number}">{number}</a></sup>"##
+                    );
+                } else {
+                    push_undefined(&mut output, label);
+                }
+            }
+            _ => output.push_str(part),
+        }
+    }
+    output
+}
+
+// Render the undefined reference as its source.
+fn push_undefined(output: &mut String, label: &str) {
+    output.push_str("[^");
+    escape_html(&mut *output, label).expect("Escape footnote failed.");
+    output.push(']');
+}
+
+fn reference_id(number: usize, count: usize) -> String {
+    if count == 1 {
+        format!("fnref-{number}")
+    } else {
+        format!("fnref-{number}-{count}")
+    }
+}
+
+// Render the definition inline, the paragraphs are separated by line breaks.
+fn inline_definition(definition: &str) -> String {
+    definition
+        .trim()
+        .trim_start_matches("<p>")
+        .trim_end_matches("</p>")
+        .replace("</p>\n<p>", "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{entity::MarkdownConfig, markdown::MarkdownRender};
+
+    #[test]
+    fn test_render_footnotes() {
+        let mut config = MarkdownConfig::default();
+        let markdown = "[^b]: Note B.\n\nA[^a] b[^b] a[^a] c[^c].\n\n[^a]: Note A.\n";
+        let html = MarkdownRender::new(&config).render_html(markdown);
+        assert_eq!(
+            html,
+            concat!(
+                r##"<p>A<sup class="footnote-ref" id="fnref-1"><a href="#fn-1">1</a></sup>"##,
+                r##" b<sup class="footnote-ref" id="fnref-2"><a href="#fn-2">2</a></sup>"##,
+                r##" a<sup class="footnote-ref" id="fnref-1-2"><a href="#fn-1">1</a></sup> c[^c].</p>"##,
+                "\n",
+                r#"<section class="footnotes"><ol>"#,
+                r##"<li id="fn-1"><p>Note A. <a class="footnote-backref" href="#fnref-1" aria-label="Back to reference 1">↩<sup>1</sup></a>"##,
+                r##" <a class="footnote-backref" href="#fnref-1-2" aria-label="Back to reference 1">↩<sup>2</sup></a></p></li>"##,
+                r##"<li id="fn-2"><p>Note B. <a class="footnote-backref" href="#fnref-2" aria-label="Back to reference 2">↩</a></p></li>"##,
+                "</ol></section>\n",
+            )
+        );
+
+        config.sidenotes = true;
+        let html = MarkdownRender::new(&config).render_html("A[^a].\n\n[^a]: Note *A*.\n");
+        assert!(html.contains(
+            r#"<span class="sidenote" role="note"><span class="sidenote-number">1</span> Note <em>A</em>.</span>"#
+        ));
+        assert!(html.contains(r#"<section class="footnotes footnotes-with-sidenotes">"#));
+    }
+
+    #[test]
+    fn test_render_nested_footnotes() {
+        let mut config = MarkdownConfig::default();
+        let markdown = "A[^a].\n\n[^a]: Note A, see[^b] and[^x].\n\n[^b]: Note B[^a].\n";
+        let html = MarkdownRender::new(&config).render_html(markdown);
+        assert!(!html.contains('\u{1}'));
+        assert_eq!(
+            html,
+            concat!(
+                r##"<p>A<sup class="footnote-ref" id="fnref-1"><a href="#fn-1">1</a></sup>.</p>"##,
+                "\n",
+                r#"<section class="footnotes"><ol>"#,
+                r##"<li id="fn-1"><p>Note A, see<sup class="footnote-ref"><a href="#fn-2">2</a></sup> and[^x]."##,
+                r##" <a class="footnote-backref" href="#fnref-1" aria-label="Back to reference 1">↩</a></p></li>"##,
+                r##"<li id="fn-2"><p>Note B<sup class="footnote-ref"><a href="#fn-1">1</a></sup>.</p></li>"##,
+                "</ol></section>\n",
+            )
+        );
+
+        config.sidenotes = true;
+        let html = MarkdownRender::new(&config).render_html(markdown);
+        assert!(!html.contains('\u{1}'));
+        assert!(html.contains(
+            r##"<span class="sidenote-number">1</span> Note A, see<sup class="footnote-ref"><a href="#fn-2">2</a></sup> and[^x].</span>"##
+        ));
+    }
+
+    #[test]
+    fn test_render_footnote_in_heading() {
+        crate::engine::init_test_tera();
+        let config = MarkdownConfig::default();
+        let mut render = MarkdownRender::new(&config);
+        let html = render.render_html("## Title[^a]\n\n[^a]: Note A.\n");
+        assert!(!html.contains('\u{1}'));
+        assert!(html.contains(
+            r##"Title<sup class="footnote-ref" id="fnref-1"><a href="#fn-1">1</a></sup>"##
+        ));
+        assert!(html.find("Title").unwrap() < html.find("footnote-ref").unwrap());
+        let toc = serde_json::to_value(&render.toc).unwrap();
+        assert_eq!(toc[0]["title"], "Title");
+        assert_eq!(toc[0]["id"], "title");
+    }
+}
@@ -0,0 +1,483 @@
+use std::{mem, ops::RangeInclusive, path::Path};
+
+use anyhow::{Context as _, Result};
+use once_cell::sync::Lazy;
+use parking_lot::{MappedRwLockReadGuard, RwLock, RwLockReadGuard};
+use pulldown_cmark::escape::escape_html;
+use syntect::{
+    dumps::from_binary,
+    easy::HighlightLines,
+    highlighting::{Theme, ThemeSet},
+    html::{
+        css_for_theme_with_class_style, start_highlighted_html_snippet,
+        styled_line_to_highlighted_html, ClassStyle, ClassedHTMLGenerator, IncludeBackground,
+    },
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+use crate::{
+    code_blocks::Fenced,
+    entity::{HighlightMode, MarkdownConfig},
+};
+
+static BUILTIN_ASSETS: Lazy<HighlightAssets> = Lazy::new(|| HighlightAssets {
+    syntax_set: from_binary(include_bytes!("../../sublime/syntaxes/newlines.packdump")),
+    theme_set: from_binary(include_bytes!("../../sublime/themes/all.themedump")),
+});
+// The builtin assets merged with the project's, `None` if the project has nothing to merge.
+static PROJECT_ASSETS: Lazy<RwLock<Option<HighlightAssets>>> = Lazy::new(|| RwLock::new(None));
+// The class style of highlighted code, prefixed to avoid conflicting with other classes.
+const HIGHLIGHT_CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+// The icon of the copy-to-clipboard button.
+const COPY_ICON: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" stroke-linecap="round" stroke-linejoin="round"><rect x="9" y="9" width="13" height="13" rx="2"></rect><path d="M5 15H4a2 2 0 0 1-2-2V4a2 2 0 0 1 2-2h9a2 2 0 0 1 2 2v1"></path></svg>"#;
+
+/// The syntaxes and themes to highlight code.
+struct HighlightAssets {
+    syntax_set: SyntaxSet,
+    theme_set: ThemeSet,
+}
+
+impl HighlightAssets {
+    // Merge the syntaxes and themes in the `dir` into the builtin ones.
+    fn load(dir: &Path) -> Result<Self> {
+        let mut builder = BUILTIN_ASSETS.syntax_set.clone().into_builder();
+        builder
+            .add_from_folder(dir, true)
+            .with_context(|| format!("Failed to load the syntaxes in `{}`", dir.display()))?;
+        let mut theme_set = ThemeSet {
+            themes: BUILTIN_ASSETS.theme_set.themes.clone(),
+        };
+        theme_set
+            .add_from_folder(dir)
+            .with_context(|| format!("Failed to load the themes in `{}`", dir.display()))?;
+        Ok(HighlightAssets {
+            syntax_set: builder.build(),
+            theme_set,
+        })
+    }
+
+    fn theme(&self, name: &str) -> Result<&Theme> {
+        self.theme_set.themes.get(name).with_context(|| {
+            format!(
+                "Unknown highlight theme `{name}`, run `zine highlight-themes` to list the available themes."
+            )
+        })
+    }
+
+    fn syntax(&self, lang: &str) -> &SyntaxReference {
+        self.syntax_set
+            .find_syntax_by_token(lang)
+            // Fallback to plain text if code block not supported
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
+    }
+}
+
+// The highlight assets of current project.
+fn assets() -> MappedRwLockReadGuard<'static, HighlightAssets> {
+    RwLockReadGuard::map(PROJECT_ASSETS.read(), |assets| {
+        assets.as_ref().unwrap_or(&BUILTIN_ASSETS)
+    })
+}
+
+/// Load the syntaxes (`.sublime-syntax`) and themes (`.tmTheme`) of the project's
+/// highlight directory, then validate the configured highlight themes.
+pub fn load_highlight_assets(source: &Path, markdown_config: &MarkdownConfig) -> Result<()> {
+    let dir = source.join(crate::ZINE_HIGHLIGHT_DIR);
+    *PROJECT_ASSETS.write() = if dir.is_dir() {
+        Some(HighlightAssets::load(&dir)?)
+    } else {
+        None
+    };
+
+    if markdown_config.highlight_code {
+        let assets = assets();
+        assets.theme(&markdown_config.highlight_theme)?;
+        if let Some(dark_theme) = markdown_config.dark_highlight_theme.as_ref() {
+            assets.theme(dark_theme)?;
+        }
+    }
+    Ok(())
+}
+
+/// List the available highlight themes, the project's themes are marked.
+pub fn list_highlight_themes<P: AsRef<Path>>(source: P) -> Result<()> {
+    let source = source.as_ref();
+    load_highlight_assets(
+        source,
+        &MarkdownConfig {
+            highlight_code: false,
+            ..Default::default()
+        },
+    )?;
+
+    let builtin_themes = &BUILTIN_ASSETS.theme_set.themes;
+    for name in assets().theme_set.themes.keys() {
+        if builtin_themes.contains_key(name) {
+            println!("{name}");
+        } else {
+            println!("{name} (project)");
+        }
+    }
+    Ok(())
+}
+
+/// Generate the highlight stylesheet of the classed highlighted code.
+///
+/// Return `None` if the code is highlighted with inline styles.
+pub fn highlight_stylesheet(markdown_config: &MarkdownConfig) -> Result<Option<String>> {
+    if markdown_config.highlight_mode != HighlightMode::Classes {
+        return Ok(None);
+    }
+
+    let assets = assets();
+    let css_for_theme = |name: &str| -> Result<String> {
+        Ok(css_for_theme_with_class_style(
+            assets.theme(name)?,
+            HIGHLIGHT_CLASS_STYLE,
+        )?)
+    };
+
+    let mut stylesheet = css_for_theme(&markdown_config.highlight_theme)?;
+    if let Some(dark_theme) = markdown_config.dark_highlight_theme.as_ref() {
+        let dark_css = css_for_theme(dark_theme)?;
+        // The dark theme applies if the reader prefers dark color scheme (and doesn't toggle
+        // to light), or the reader toggles to dark explicitly.
+        stylesheet.push_str("@media (prefers-color-scheme: dark) {\n");
+        stylesheet.push_str(&scope_css(&dark_css, r#":root:not([data-theme="light"])"#));
+        stylesheet.push_str("}\n");
+        stylesheet.push_str(&scope_css(&dark_css, r#":root[data-theme="dark"]"#));
+    }
+    Ok(Some(stylesheet))
+}
+
+// Prefix every selector of the syntect generated `css` with `scope`.
+fn scope_css(css: &str, scope: &str) -> String {
+    css.lines()
+        .map(|line| match line.strip_suffix(" {") {
+            Some(selectors) => {
+                let selectors = selectors
+                    .split(", ")
+                    .map(|selector| format!("{scope} {selector}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{selectors} {{\n")
+            }
+            None => format!("{line}\n"),
+        })
+        .collect()
+}
+
+/// A normal fenced code block, the fenced options are:
+///
+/// ```markdown
+/// ```rust, linenos, hl_lines: 3-5 8, title: src/main.rs
+/// ```
+pub struct CodeSnippet<'a> {
+    lang: &'a str,
+    code: &'a str,
+    // The anchor id prefix of the lines.
+    id: String,
+    title: Option<&'a str>,
+    linenos: bool,
+    hl_lines: Vec<RangeInclusive<usize>>,
+}
+
+impl<'a> CodeSnippet<'a> {
+    pub fn new(fenced: &Fenced<'a>, code: &'a str, id: String) -> Self {
+        let options = &fenced.options;
+        CodeSnippet {
+            lang: fenced.name,
+            code,
+            id,
+            title: options.get("title").copied().filter(|t| !t.is_empty()),
+            linenos: options.get("linenos").is_some_and(|v| *v != "false"),
+            hl_lines: options
+                .get("hl_lines")
+                .map(|value| parse_hl_lines(value))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn is_highlighted_line(&self, number: usize) -> bool {
+        self.hl_lines.iter().any(|range| range.contains(&number))
+    }
+
+    /// Render the code block, highlight the syntax if `highlight_code` enabled.
+    pub fn render(&self, markdown_config: &MarkdownConfig) -> Result<String> {
+        let (pre, lines) = if markdown_config.highlight_code {
+            self.highlight(markdown_config)?
+        } else {
+            let mut escaped = String::new();
+            escape_html(&mut escaped, self.code)?;
+            ("<pre>".to_owned(), split_html_lines(&escaped))
+        };
+
+        let mut html = r#"<div class="code-block" data-lang=""#.to_owned();
+        escape_html(&mut html, self.lang)?;
+        html.push_str(r#"">"#);
+        if let Some(title) = self.title {
+            html.push_str(r#"<div class="code-block-title">"#);
+            escape_html(&mut html, title)?;
+            html.push_str("</div>");
+        }
+        html.push_str(&pre);
+        if self.linenos || !self.hl_lines.is_empty() {
+            for (index, line) in lines.iter().enumerate() {
+                let number = index + 1;
+                let class = if self.is_highlighted_line(number) {
+                    "line line-highlighted"
+                } else {
+                    "line"
+                };
+                if self.linenos {
+                    let id = format!("{}-L{number}", self.id);
+                    html.push_str(&format!(
+                        r##"<span class="{class}" id="{id}"><a class="line-number" href="#{id}" data-line="{number}"></a>{line}</span>"##
+                    ));
+                } else {
+                    html.push_str(&format!(r#"<span class="{class}">{line}</span>"#));
+                }
+            }
+        } else {
+            html.extend(lines);
+        }
+        html.push_str("</pre>");
+        html.push_str(&format!(
+            r#"<button class="code-block-copy" type="button" aria-label="Copy">{COPY_ICON}</button></div>"#
+        ));
+        Ok(html)
+    }
+
+    // Highlight the code, return the opening pre tag and the highlighted lines.
+    fn highlight(&self, markdown_config: &MarkdownConfig) -> Result<(String, Vec<String>)> {
+        let assets = assets();
+        let syntax = assets.syntax(self.lang);
+
+        if markdown_config.highlight_mode == HighlightMode::Inline {
+            let theme = assets.theme(&markdown_config.highlight_theme)?;
+            let (pre, background) = start_highlighted_html_snippet(theme);
+            let mut highlighter = HighlightLines::new(syntax, theme);
+            let mut html = String::new();
+            for line in LinesWithEndings::from(self.code) {
+                let regions = highlighter.highlight_line(line, &assets.syntax_set)?;
+                html.push_str(&styled_line_to_highlighted_html(
+                    &regions,
+                    IncludeBackground::IfDifferent(background),
+                )?);
+            }
+            return Ok((pre.trim_end().to_owned(), split_html_lines(&html)));
+        }
+
+        // Highlight with css classes, the colors are switched by the highlight stylesheet.
+        let mut generator = ClassedHTMLGenerator::new_with_class_style(
+            syntax,
+            &assets.syntax_set,
+            HIGHLIGHT_CLASS_STYLE,
+        );
+        for line in LinesWithEndings::from(self.code) {
+            generator.parse_html_for_line_which_includes_newline(line)?;
+        }
+        Ok((
+            r#"<pre class="hl-code">"#.to_owned(),
+            split_html_lines(&generator.finalize()),
+        ))
+    }
+}
+
+// Parse the highlighted lines, such as `3-5 8`.
+fn parse_hl_lines(value: &str) -> Vec<RangeInclusive<usize>> {
+    value
+        .split_whitespace()
+        .filter_map(|part| {
+            let range = match part.split_once('-') {
+                Some((start, end)) => start.parse().ok().zip(end.parse().ok()),
+                None => part.parse().ok().map(|line| (line, line)),
+            };
+            if range.is_none() {
+                println!("Warning: invalid highlighted lines: {part}");
+            }
+            range.map(|(start, end)| start..=end)
+        })
+        .collect()
+}
+
+// Split the highlighted html into lines, every line ends with a newline.
+// The spans across lines are closed at the end of the line and
+// reopened at the start of the next line.
+fn split_html_lines(html: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut open_spans = Vec::new();
+    let mut line = String::new();
+    let mut has_text = false;
+    let mut rest = html;
+    while let Some(index) = rest.find(['<', '\n']) {
+        if index > 0 {
+            line.push_str(&rest[..index]);
+            has_text = true;
+        }
+        rest = &rest[index..];
+        if let Some(remaining) = rest.strip_prefix('\n') {
+            line.push_str(&"</span>".repeat(open_spans.len()));
+            line.push('\n');
+            lines.push(mem::take(&mut line));
+            line.extend(open_spans.iter().copied());
+            has_text = false;
+            rest = remaining;
+        } else {
+            let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+            let tag = &rest[..end];
+            if tag.starts_with("</") {
+                open_spans.pop();
+            } else {
+                open_spans.push(tag);
+            }
+            line.push_str(tag);
+            rest = &rest[end..];
+        }
+    }
+    if !rest.is_empty() {
+        line.push_str(rest);
+        has_text = true;
+    }
+    if has_text {
+        line.push('\n');
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        highlight_stylesheet, parse_hl_lines, scope_css, split_html_lines, CodeSnippet,
+        HighlightAssets,
+    };
+    use crate::{
+        code_blocks::Fenced,
+        entity::{HighlightMode, MarkdownConfig},
+    };
+    use test_case::test_case;
+
+    #[test]
+    fn test_scope_css() {
+        assert_eq!(
+            scope_css(".hl-code {\n color: #f8f8f2;\n}\n.hl-comment, .hl-string.hl-quoted {\n font-style: italic;\n}", ":root[data-theme=\"dark\"]"),
+            ":root[data-theme=\"dark\"] .hl-code {\n color: #f8f8f2;\n}\n:root[data-theme=\"dark\"] .hl-comment, :root[data-theme=\"dark\"] .hl-string.hl-quoted {\n font-style: italic;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_highlight_stylesheet() {
+        let mut config = MarkdownConfig::default();
+        assert!(highlight_stylesheet(&config).unwrap().is_none());
+
+        config.highlight_mode = HighlightMode::Classes;
+        let stylesheet = highlight_stylesheet(&config).unwrap().unwrap();
+        assert!(stylesheet.contains(".hl-code {"));
+        assert!(!stylesheet.contains("@media"));
+
+        config.dark_highlight_theme = Some("monokai".into());
+        let stylesheet = highlight_stylesheet(&config).unwrap().unwrap();
+        assert!(stylesheet.starts_with("/*"));
+        assert!(stylesheet.contains("@media (prefers-color-scheme: dark) {"));
+        assert!(stylesheet.contains(":root:not([data-theme=\"light\"]) .hl-code {"));
+        assert!(stylesheet.contains(":root[data-theme=\"dark\"] .hl-code {"));
+
+        config.dark_highlight_theme = Some("missing".into());
+        assert!(highlight_stylesheet(&config).is_err());
+    }
+
+    #[test]
+    fn test_load_highlight_assets() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::write(
+            dir.join("zine-test.sublime-syntax"),
+            "%YAML 1.2\n---\nname: ZineTest\nfile_extensions: [zinetest]\nscope: source.zinetest\ncontexts:\n  main:\n    - match: '#.*$'\n      scope: comment.line.zinetest\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("zine-test.tmTheme"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict><key>name</key><string>Zine Test</string><key>settings</key><array>
+<dict><key>settings</key><dict><key>background</key><string>#FFFFFF</string><key>foreground</key><string>#000000</string></dict></dict>
+</array></dict></plist>"#,
+        )
+        .unwrap();
+
+        let assets = HighlightAssets::load(dir).unwrap();
+        assert!(assets.theme("zine-test").is_ok());
+        assert!(assets.theme("monokai").is_ok());
+        assert_eq!(assets.syntax("zinetest").name, "ZineTest");
+        assert_eq!(assets.syntax("rust").name, "Rust");
+        let err = assets.theme("missing").unwrap_err();
+        assert!(err.to_string().contains("zine highlight-themes"));
+    }
+
+    #[test_case("3-5 8", vec![3..=5, 8..=8]; "ranges")]
+    #[test_case("2", vec![2..=2]; "single")]
+    #[test_case("1-x 4", vec![4..=4]; "invalid")]
+    #[test_case("", vec![]; "empty")]
+    fn test_parse_hl_lines(value: &str, expected: Vec<std::ops::RangeInclusive<usize>>) {
+        assert_eq!(parse_hl_lines(value), expected);
+    }
+
+    #[test_case("a\nb", vec!["a\n", "b\n"]; "plain")]
+    #[test_case("a\n\nb\n", vec!["a\n", "\n", "b\n"]; "empty line")]
+    #[test_case(
+        r#"<span class="a">x<span class="b">/*
+*/</span></span>"#,
+        vec![
+            "<span class=\"a\">x<span class=\"b\">/*</span></span>\n",
+            "<span class=\"a\"><span class=\"b\">*/</span></span>\n"
+        ];
+        "spans across lines"
+    )]
+    fn test_split_html_lines(html: &str, expected: Vec<&str>) {
+        assert_eq!(split_html_lines(html), expected);
+    }
+
+    #[test]
+    fn test_render_code_snippet() {
+        let mut config = MarkdownConfig {
+            highlight_code: false,
+            ..Default::default()
+        };
+        let fenced = Fenced::parse("rust, linenos, hl_lines: 2, title: src/main.rs").unwrap();
+        let html = CodeSnippet::new(&fenced, "fn main() {\n    a < b;\n}\n", "code-1".into())
+            .render(&config)
+            .unwrap();
+        assert!(html.starts_with(concat!(
+            r#"<div class="code-block" data-lang="rust"><div class="code-block-title">src/main.rs</div><pre>"#,
+            r##"<span class="line" id="code-1-L1"><a class="line-number" href="#code-1-L1" data-line="1"></a>fn main() {"##,
+            "\n</span>",
+            r##"<span class="line line-highlighted" id="code-1-L2"><a class="line-number" href="#code-1-L2" data-line="2"></a>    a &lt; b;"##,
+            "\n</span>",
+            r##"<span class="line" id="code-1-L3"><a class="line-number" href="#code-1-L3" data-line="3"></a>}"##,
+            "\n</span></pre>",
+        )));
+        assert!(html.ends_with("</button></div>"));
+
+        config.highlight_code = true;
+        config.highlight_mode = HighlightMode::Classes;
+        let fenced = Fenced::parse("rust").unwrap();
+        let html = CodeSnippet::new(&fenced, "/*\n*/\n", "code-2".into())
+            .render(&config)
+            .unwrap();
+        assert!(
+            html.starts_with(r#"<div class="code-block" data-lang="rust"><pre class="hl-code">"#)
+        );
+        assert!(!html.contains("code-2-L1"));
+
+        // The language of the fence info string is escaped.
+        config.highlight_code = false;
+        let fenced = Fenced::parse(r#"a"onmouseover=alert(1)"#).unwrap();
+        let html = CodeSnippet::new(&fenced, "a\n", "code-3".into())
+            .render(&config)
+            .unwrap();
+        assert!(html.starts_with(
+            r#"<div class="code-block" data-lang="a&quot;onmouseover=alert(1)"><pre>"#
+        ));
+    }
+}
@@ -0,0 +1,94 @@
+use std::{mem, ops::Range};
+
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag};
+
+/// A span of the markdown source rendered as inline html, such as the math and shortcode.
+#[derive(Debug)]
+pub struct InlineSpan {
+    pub range: Range<usize>,
+    pub html: String,
+}
+
+/// Replace the spans of the markdown events with their rendered html.
+///
+/// The `events` must be the offset events of the `markdown`, the `spans` must be
+/// sorted and inside the text.
+pub fn replace_inline_spans<'a>(
+    markdown: &'a str,
+    events: impl Iterator<Item = (Event<'a>, Range<usize>)>,
+    mut spans: Vec<InlineSpan>,
+) -> impl Iterator<Item = (Event<'a>, Range<usize>)> {
+    let mut rendered = vec![false; spans.len()];
+    let mut index = 0;
+    events.flat_map(move |(event, range)| {
+        // Skip the spans before this event.
+        while spans
+            .get(index)
+            .is_some_and(|span| span.range.end <= range.start)
+        {
+            index += 1;
+        }
+        let overlapping = (index..spans.len())
+            .take_while(|i| spans[*i].range.start < range.end)
+            .collect::<Vec<_>>();
+        if overlapping.is_empty() {
+            return vec![(event, range)];
+        }
+
+        let mut events = Vec::new();
+        let mut cursor = range.start;
+        for i in overlapping {
+            let span_range = spans[i].range.clone();
+            if let Event::Text(_) = event {
+                // Keep the text around the span.
+                if span_range.start > cursor {
+                    let text = &markdown[cursor..span_range.start];
+                    events.push((Event::Text(text.into()), cursor..span_range.start));
+                }
+            } else if span_range.start > range.start || span_range.end < range.end {
+                // The tags wrap the span, such as the paragraph and emphasis, are kept.
+                return vec![(event, range)];
+            }
+            // The span may cross several text events, only render it once.
+            if !rendered[i] {
+                rendered[i] = true;
+                let html = mem::take(&mut spans[i].html);
+                events.push((Event::Html(CowStr::from(html)), span_range.clone()));
+            }
+            cursor = cursor.max(span_range.end);
+        }
+        if matches!(event, Event::Text(_)) && cursor < range.end {
+            events.push((
+                Event::Text(markdown[cursor..range.end].into()),
+                cursor..range.end,
+            ));
+        }
+        events
+    })
+}
+
+/// The ranges of the text, not in code or html.
+pub fn text_ranges(markdown: &str) -> Vec<Range<usize>> {
+    let mut text_ranges = Vec::new();
+    let mut in_code_block = false;
+    for (event, range) in Parser::new_ext(markdown, Options::all()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Text(_) if !in_code_block => text_ranges.push(range),
+            _ => {}
+        }
+    }
+    text_ranges
+}
+
+/// The line and column (both start from 1) of the offset, for error reporting.
+pub fn line_column(markdown: &str, offset: usize) -> (usize, usize) {
+    let before = &markdown[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before[before.rfind('\n').map_or(0, |i| i + 1)..]
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
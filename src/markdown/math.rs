@@ -0,0 +1,270 @@
+use std::{borrow::Cow, ops::Range};
+
+use dashmap::DashMap;
+#[cfg(feature = "math")]
+use katex::{Opts, OutputType};
+use once_cell::sync::Lazy;
+use pulldown_cmark::{escape::escape_html, Event};
+
+use super::inline::{replace_inline_spans, text_ranges, InlineSpan};
+
+/// The rendered formulas, keyed by the TeX source and display mode.
+///
+/// Rendering with KaTeX is expensive, the formulas are rendered only once
+/// across the articles and the rebuilds of `zine serve`.
+static RENDERED_MATH: Lazy<DashMap<(String, bool), String>> = Lazy::new(DashMap::new);
+
+/// A math formula in the markdown source, `$inline$` or `$$display$$`.
+#[derive(Debug, PartialEq, Eq)]
+struct MathSpan {
+    // The range including the delimiters.
+    range: Range<usize>,
+    // The range of the TeX source.
+    tex: Range<usize>,
+    display: bool,
+}
+
+/// Render the TeX formula to MathML at build time, no client-side math library needed.
+///
+/// If the formula is invalid, or the `math` feature is disabled,
+/// the TeX source is rendered as code.
+pub fn render_math(tex: &str, display: bool) -> String {
+    let key = (tex.trim().to_owned(), display);
+    if let Some(html) = RENDERED_MATH.get(&key) {
+        return html.clone();
+    }
+    let html = render_katex(&key.0, display).unwrap_or_else(|| {
+        let mut html = String::from(r#"<code class="math-error">"#);
+        escape_html(&mut html, &key.0).expect("Escape math failed.");
+        html.push_str("</code>");
+        html
+    });
+    RENDERED_MATH.insert(key, html.clone());
+    html
+}
+
+// KaTeX reuses a JS engine per thread, the engine is initialized
+// by the first formula rendered in each thread.
+#[cfg(feature = "math")]
+fn render_katex(tex: &str, display: bool) -> Option<String> {
+    let mut opts = Opts::default();
+    opts.set_display_mode(display);
+    opts.set_output_type(OutputType::Mathml);
+    opts.set_throw_on_error(true);
+    match katex::render_with_opts(tex, opts) {
+        Ok(html) => Some(html),
+        Err(err) => {
+            println!("Warning: render math `{tex}` failed: {err}");
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "math"))]
+fn render_katex(_tex: &str, _display: bool) -> Option<String> {
+    None
+}
+
+/// Replace the math formulas of the markdown events with the rendered math.
+///
+/// The `events` must be the offset events of the `markdown`.
+pub fn render_math_events<'a>(
+    markdown: &'a str,
+    events: impl Iterator<Item = (Event<'a>, Range<usize>)>,
+) -> impl Iterator<Item = (Event<'a>, Range<usize>)> {
+    let spans = find_math_spans(markdown)
+        .into_iter()
+        .map(|span| InlineSpan {
+            html: render_math(&markdown[span.tex], span.display),
+            range: span.range,
+        })
+        .collect();
+    replace_inline_spans(markdown, events, spans)
+}
+
+/// Replace the math formulas with their TeX source, which is readable as plain text.
+pub fn strip_math(markdown: &str) -> Cow<'_, str> {
+    let spans = find_math_spans(markdown);
+    if spans.is_empty() {
+        return Cow::Borrowed(markdown);
+    }
+
+    let mut stripped = String::new();
+    let mut cursor = 0;
+    for span in spans {
+        stripped.push_str(&markdown[cursor..span.range.start]);
+        stripped.push_str(markdown[span.tex].trim());
+        cursor = span.range.end;
+    }
+    stripped.push_str(&markdown[cursor..]);
+    Cow::Owned(stripped)
+}
+
+// Find the math formulas, the delimiters must be in the text, not in code or html.
+fn find_math_spans(markdown: &str) -> Vec<MathSpan> {
+    let text_ranges = text_ranges(markdown);
+    let is_text = |offset: usize| {
+        text_ranges
+            .iter()
+            .any(|range: &Range<usize>| range.contains(&offset))
+    };
+
+    let bytes = markdown.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            // Skip the escaped char, such as `\$`.
+            b'\\' => i += 1,
+            b'$' if is_text(i) => {
+                let span = if bytes.get(i + 1) == Some(&b'$') {
+                    find_display_math(markdown, i, is_text)
+                } else {
+                    find_inline_math(markdown, i, is_text)
+                };
+                if let Some(span) = span {
+                    i = span.range.end;
+                    spans.push(span);
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    spans
+}
+
+// Find the closing `$$` of the display math starts at `start`.
+fn find_display_math(
+    markdown: &str,
+    start: usize,
+    is_text: impl Fn(usize) -> bool,
+) -> Option<MathSpan> {
+    let tex_start = start + 2;
+    let mut offset = tex_start;
+    while let Some(index) = markdown[offset..].find("$$") {
+        let end = offset + index;
+        if is_text(end) && !is_escaped(markdown, end) {
+            if markdown[tex_start..end].trim().is_empty() {
+                return None;
+            }
+            return Some(MathSpan {
+                range: start..end + 2,
+                tex: tex_start..end,
+                display: true,
+            });
+        }
+        offset = end + 2;
+    }
+    None
+}
+
+// Find the closing `$` of the inline math starts at `start`.
+//
+// Like Pandoc, the opening `$` must have a non-space char immediately to its right,
+// while the closing `$` must have a non-space char immediately to its left,
+// and must not be followed immediately by a digit. Thus, `$20,000 and $30,000`
+// won't parse as math.
+fn find_inline_math(
+    markdown: &str,
+    start: usize,
+    is_text: impl Fn(usize) -> bool,
+) -> Option<MathSpan> {
+    let tex_start = start + 1;
+    if !markdown[tex_start..].starts_with(|c: char| !c.is_whitespace()) {
+        return None;
+    }
+
+    let mut offset = tex_start;
+    while let Some(index) = markdown[offset..].find(['$', '\n']) {
+        let end = offset + index;
+        // The inline math can't cross paragraphs.
+        if markdown[end..].starts_with("\n\n") {
+            return None;
+        }
+        let is_closing = markdown[end..].starts_with('$')
+            && end > tex_start
+            && is_text(end)
+            && !is_escaped(markdown, end)
+            && !markdown[..end].ends_with(char::is_whitespace)
+            && !markdown[end + 1..].starts_with(|c: char| c.is_ascii_digit());
+        if is_closing {
+            return Some(MathSpan {
+                range: start..end + 1,
+                tex: tex_start..end,
+                display: false,
+            });
+        }
+        offset = end + 1;
+    }
+    None
+}
+
+// Whether the char at `offset` is escaped by odd backslashes.
+fn is_escaped(markdown: &str, offset: usize) -> bool {
+    let backslashes = markdown[..offset]
+        .bytes()
+        .rev()
+        .take_while(|b| *b == b'\\')
+        .count();
+    backslashes % 2 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_math_spans, render_math, strip_math, RENDERED_MATH};
+    use test_case::test_case;
+
+    #[test_case("$x^2$", &[("x^2", false)]; "inline")]
+    #[test_case("a $x$ and $$\ny = 1\n$$ b", &[("x", false), ("\ny = 1\n", true)]; "inline and display")]
+    #[test_case("It costs $20,000 and $30,000.", &[]; "currency")]
+    #[test_case("$ x$ and $x $", &[]; "spaces")]
+    #[test_case(r"\$x$ and $a\$b$", &[(r"a\$b", false)]; "escaped")]
+    #[test_case("`$x$` and\n```\n$$y$$\n```", &[]; "code")]
+    #[test_case("$x\n\ny$", &[]; "cross paragraphs")]
+    #[test_case("$$ $$", &[]; "empty display")]
+    fn test_find_math_spans(markdown: &str, expected: &[(&str, bool)]) {
+        let spans = find_math_spans(markdown)
+            .into_iter()
+            .map(|span| (&markdown[span.tex], span.display))
+            .collect::<Vec<_>>();
+        assert_eq!(spans, expected);
+    }
+
+    #[test_case("The $E = mc^2$ formula", "The E = mc^2 formula")]
+    #[test_case("No math, $5", "No math, $5")]
+    fn test_strip_math(markdown: &str, expected: &str) {
+        assert_eq!(strip_math(markdown), expected);
+    }
+
+    #[cfg(feature = "math")]
+    #[test]
+    fn test_render_math_events() {
+        use super::render_math_events;
+        use pulldown_cmark::{html, Parser};
+
+        let markdown = r"Euler: $e^{i\pi} + 1 = 0$, *done* $$x$$";
+        let events = render_math_events(markdown, Parser::new(markdown).into_offset_iter());
+        let mut html = String::new();
+        html::push_html(&mut html, events.map(|(event, _)| event));
+        assert!(html.starts_with(r#"<p>Euler: <span class="katex"><math"#));
+        assert!(html.contains("<mi>π</mi>"));
+        assert!(html.contains(r#", <em>done</em> <span class="katex"><math xmlns="http://www.w3.org/1998/Math/MathML" display="block">"#));
+        assert!(!html.contains('$'));
+    }
+
+    #[test]
+    fn test_render_invalid_math() {
+        assert_eq!(
+            render_math(r"\frac{1", false),
+            r#"<code class="math-error">\frac{1</code>"#
+        );
+        // The rendered formula is cached.
+        assert!(RENDERED_MATH.contains_key(&(r"\frac{1".to_owned(), false)));
+        assert_eq!(
+            render_math(r" \frac{1 ", false),
+            r#"<code class="math-error">\frac{1</code>"#
+        );
+    }
+}
@@ -1,7 +1,16 @@
 use pulldown_cmark::Event::{Code, End, HardBreak, Rule, SoftBreak, Start, Text};
 use pulldown_cmark::{Options, Parser, Tag};
 
+mod assets;
+mod footnote;
+mod highlight;
+mod inline;
+mod math;
 mod render;
+mod shortcode;
+pub use assets::ColocatedAssets;
+pub use highlight::{highlight_stylesheet, list_highlight_themes, load_highlight_assets};
+pub use math::render_math;
 pub use render::MarkdownRender;
 
 /// Extract the description from markdown content.
@@ -18,10 +27,16 @@ pub fn extract_description(markdown: &str) -> String {
         .filter_map(|line| {
             // Ignore heading, image line.
             let line = line.trim();
-            if line.is_empty() || line.starts_with(['#', '!']) {
+            // Ignore the delimiter line of display math and the admonition marker line.
+            if line.is_empty()
+                || line.starts_with(['#', '!'])
+                || line == "$$"
+                || line.trim_start_matches('>').trim_start().starts_with("[!")
+            {
                 None
             } else {
-                let raw = strip_markdown(line);
+                // Keep the TeX source of math and the text of shortcodes, which are readable.
+                let raw = strip_markdown(&math::strip_math(&shortcode::strip_shortcodes(line)));
                 // If the stripped raw text is empty, we step to next one.
                 if raw == "\n" || raw.is_empty() {
                     None
@@ -135,6 +150,17 @@ mod tests {
         assert_eq!("a 'aa' a", extract_description(markdown));
     }
 
+    #[test_case("The $E = mc^2$ formula\n$$\nx^2\n$$", "The E = mc^2 formula\nx^2"; "math")]
+    fn test_extract_decription_with_math(markdown: &str, expected: &str) {
+        assert_eq!(expected, extract_description(markdown));
+    }
+
+    #[test]
+    fn test_extract_decription_with_admonition() {
+        let markdown = "> [!NOTE]\n> Read the docs.";
+        assert_eq!("Read the docs.\n", extract_description(markdown));
+    }
+
     #[test]
     fn test_extract_decription4() {
         assert_eq!("", extract_description(""));
@@ -1,41 +1,37 @@
 use std::{collections::BTreeSet, mem};
 
 use crate::{
-    code_blocks::{AuthorCode, CodeBlock, Fenced, InlineLink},
+    code_blocks::{Admonition, AuthorCode, CodeBlock, Fenced, InlineLink},
     data, engine,
     entity::MarkdownConfig,
 };
 
-use once_cell::sync::Lazy;
+use super::{footnote::Footnotes, highlight::CodeSnippet, math, shortcode, ColocatedAssets};
+
 use pulldown_cmark::*;
 use serde::Serialize;
-use syntect::{
-    dumps::from_binary, highlighting::ThemeSet, html::highlighted_html_for_string,
-    parsing::SyntaxSet,
-};
 use tera::Context;
 use tokio::{runtime::Handle, task};
 
-static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(|| {
-    let syntax_set: SyntaxSet =
-        from_binary(include_bytes!("../../sublime/syntaxes/newlines.packdump"));
-    syntax_set
-});
-static THEME_SET: Lazy<ThemeSet> = Lazy::new(|| {
-    let theme_set: ThemeSet = from_binary(include_bytes!("../../sublime/themes/all.themedump"));
-    theme_set
-});
-
 /// Markdown html render.
 pub struct MarkdownRender<'a> {
     markdown_config: &'a MarkdownConfig,
+    // The co-located assets referenced by relative urls.
+    assets: Option<ColocatedAssets>,
     code_block_fenced: Option<CowStr<'a>>,
+    // The count of rendered normal code blocks, used to generate unique line anchors.
+    code_block_count: usize,
+    // The count of rendered custom code blocks, used to generate unique ids.
+    custom_block_count: usize,
     // Whether we are processing image parsing
     processing_image: bool,
     // The alt of the processing image
     image_alt: Option<CowStr<'a>>,
     heading: Option<Heading<'a>>,
     levels: BTreeSet<usize>,
+    footnotes: Footnotes<'a>,
+    // The depth of the blockquotes inside the rendered admonition, which are skipped.
+    admonition_depth: usize,
     /// Table of content.
     pub toc: Vec<Heading<'a>>,
 }
@@ -109,15 +105,27 @@ impl<'a> MarkdownRender<'a> {
     pub fn new(markdown_config: &'a MarkdownConfig) -> Self {
         MarkdownRender {
             markdown_config,
+            assets: None,
             code_block_fenced: None,
+            code_block_count: 0,
+            custom_block_count: 0,
             processing_image: false,
             image_alt: None,
             heading: None,
             levels: BTreeSet::new(),
+            footnotes: Footnotes::default(),
+            admonition_depth: 0,
             toc: Vec::new(),
         }
     }
 
+    /// Set the co-located assets, the relative urls of images and links
+    /// would be rewritten to the copied assets.
+    pub fn set_colocated_assets(&mut self, assets: ColocatedAssets) -> &mut Self {
+        self.assets = Some(assets);
+        self
+    }
+
     /// Rebuild the relative depth of toc items.
     pub fn rebuild_toc_depth(&mut self) {
         let depths = Vec::from_iter(&self.levels);
@@ -130,41 +138,70 @@ impl<'a> MarkdownRender<'a> {
         });
     }
 
-    fn highlight_syntax(&self, lang: &str, text: &str) -> String {
-        let theme = match THEME_SET.themes.get(&self.markdown_config.highlight_theme) {
-            Some(theme) => theme,
-            None => panic!(
-                "No theme: `{}` founded",
-                self.markdown_config.highlight_theme
-            ),
-        };
-
-        let syntax = SYNTAX_SET
-            .find_syntax_by_token(lang)
-            // Fallback to plain text if code block not supported
-            .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
-        highlighted_html_for_string(text, &SYNTAX_SET, syntax, theme).expect("Highlight failed")
-    }
-
     /// Render markdown to HTML.
     pub fn render_html(&mut self, markdown: &'a str) -> String {
         let parser_events_iter = Parser::new_ext(markdown, Options::all()).into_offset_iter();
-        let events = parser_events_iter
-            .into_iter()
-            .filter_map(move |(event, _)| match event {
-                Event::Start(tag) => self.visit_start_tag(&tag).resolve(|| Event::Start(tag)),
-                Event::End(tag) => self.visit_end_tag(&tag).resolve(|| Event::End(tag)),
-                Event::Code(code) => self.visit_code(&code).resolve(|| Event::Code(code)),
-                Event::Text(text) => self
+        let parser_events_iter = shortcode::render_shortcode_events(
+            markdown,
+            math::render_math_events(markdown, parser_events_iter),
+        );
+        let this = &mut *self;
+        let events = parser_events_iter.filter_map(move |(event, range)| {
+            // The events of admonition are rendered by the callout.
+            if this.skip_admonition(&event) {
+                return None;
+            }
+            let event = match event {
+                Event::Start(Tag::BlockQuote) => this
+                    .visit_blockquote(&markdown[range.clone()], quote_depth(markdown, range.start))
+                    .resolve(|| Event::Start(Tag::BlockQuote)),
+                Event::Start(tag) => this.visit_start_tag(&tag).resolve(|| Event::Start(tag)),
+                Event::End(tag) => this.visit_end_tag(&tag).resolve(|| Event::End(tag)),
+                Event::Code(code) => this.visit_code(&code).resolve(|| Event::Code(code)),
+                Event::Html(html) => this.visit_html(&html).resolve(|| Event::Html(html)),
+                Event::Text(text) => this
                     .visit_text(&text)
                     // Not a code block inside text, or the code block's fenced is unsupported.
                     // We still need record this text event.
                     .resolve(|| Event::Text(text)),
+                // The reference is rendered inside the heading if any.
+                Event::FootnoteReference(label) => match this.footnotes.reference(&label) {
+                    Event::Html(html) => this.visit_html(&html).resolve(|| Event::Html(html)),
+                    event => Some(event),
+                },
                 _ => Some(event),
-            });
+            }?;
+            // The events of footnote definitions are rendered with the footnotes.
+            this.footnotes.collect(event)
+        });
         let mut html = String::new();
         html::push_html(&mut html, events);
-        html
+        mem::take(&mut self.footnotes).render(html, self.markdown_config.sidenotes)
+    }
+
+    fn skip_admonition(&mut self, event: &Event<'a>) -> bool {
+        if self.admonition_depth == 0 {
+            return false;
+        }
+        match event {
+            Event::Start(Tag::BlockQuote) => self.admonition_depth += 1,
+            Event::End(Tag::BlockQuote) => self.admonition_depth -= 1,
+            _ => {}
+        }
+        true
+    }
+
+    fn visit_blockquote(&mut self, blockquote: &str, depth: usize) -> Visiting {
+        match Admonition::parse(blockquote, depth) {
+            Some(admonition) => {
+                self.admonition_depth = 1;
+                let html = admonition
+                    .render(self.markdown_config)
+                    .expect("Render admonition failed.");
+                Visiting::Event(Event::Html(html.into()))
+            }
+            None => Visiting::NotChanged,
+        }
     }
 
     fn visit_start_tag(&mut self, tag: &Tag<'a>) -> Visiting {
@@ -177,10 +214,29 @@ impl<'a> MarkdownRender<'a> {
                 self.processing_image = true;
                 Visiting::Ignore
             }
+            Tag::FootnoteDefinition(label) => {
+                self.footnotes.start_definition(label);
+                Visiting::Ignore
+            }
             Tag::Heading(level, id, _) => {
                 self.heading = Some(Heading::new(*level as usize, *id));
                 Visiting::Ignore
             }
+            Tag::Link(link_type, url, title) if self.assets.is_some() => {
+                let link = self
+                    .assets
+                    .as_ref()
+                    .and_then(|assets| assets.resolve(url))
+                    .map(|url| Tag::Link(*link_type, url.into(), title.to_string().into()));
+                if let Some(heading) = self.heading.as_mut() {
+                    heading.push_event(Event::Start(link.unwrap_or_else(|| tag.to_owned())));
+                    Visiting::Ignore
+                } else {
+                    link.map_or(Visiting::NotChanged, |link| {
+                        Visiting::Event(Event::Start(link))
+                    })
+                }
+            }
             _ => {
                 if let Some(heading) = self.heading.as_mut() {
                     heading.push_event(Event::Start(tag.to_owned()));
@@ -197,17 +253,31 @@ impl<'a> MarkdownRender<'a> {
             Tag::Image(_, src, title) => {
                 let alt = self.image_alt.take().unwrap_or_else(|| CowStr::from(""));
                 self.processing_image = false;
+                let src = self
+                    .assets
+                    .as_ref()
+                    .and_then(|assets| assets.resolve(src))
+                    .map(CowStr::from)
+                    .unwrap_or_else(|| src.clone());
 
-                // Add loading="lazy" attribute for markdown image.
-                Visiting::Event(Event::Html(
-                    format!("<img src=\"{src}\" alt=\"{alt}\" title=\"{title}\" loading=\"lazy\">")
-                        .into(),
-                ))
+                // Render markdown image with responsive variants if possible.
+                let mut context = Context::new();
+                context.insert("src", src.as_ref());
+                context.insert("alt", alt.as_ref());
+                context.insert("title", title.as_ref());
+                let html = engine::get_tera()
+                    .render("image.jinja", &context)
+                    .expect("Render image failed.");
+                Visiting::Event(Event::Html(html.into()))
             }
             Tag::CodeBlock(_) => {
                 self.code_block_fenced = None;
                 Visiting::Ignore
             }
+            Tag::FootnoteDefinition(_) => {
+                self.footnotes.end_definition();
+                Visiting::Ignore
+            }
             Tag::Heading(..) => {
                 if let Some(mut heading) = self.heading.take() {
                     self.levels.insert(heading.level);
@@ -246,25 +316,43 @@ impl<'a> MarkdownRender<'a> {
         if let Some(input) = self.code_block_fenced.as_ref() {
             let fenced = Fenced::parse(input).unwrap();
             if fenced.is_custom_code_block() {
+                self.custom_block_count += 1;
+                let index = self.custom_block_count;
                 // Block in place to execute async task
                 let rendered_html = task::block_in_place(|| {
-                    Handle::current().block_on(async { fenced.render_code_block(text).await })
+                    Handle::current()
+                        .block_on(async { fenced.render_code_block(text, index).await })
                 });
-                if let Some(html) = rendered_html {
-                    return Visiting::Event(Event::Html(html.into()));
-                }
-            } else if self.markdown_config.highlight_code {
-                // Syntax highlight
-                let html = self.highlight_syntax(fenced.name, text);
-                return Visiting::Event(Event::Html(html.into()));
-            } else {
-                return Visiting::Event(Event::Html(format!("<pre>{}</pre>", text).into()));
+                return match rendered_html {
+                    Some(html) => Visiting::Event(Event::Html(html.into())),
+                    None => Visiting::NotChanged,
+                };
             }
+
+            self.code_block_count += 1;
+            let id = format!("code-{}", self.code_block_count);
+            let html = CodeSnippet::new(&fenced, text, id)
+                .render(self.markdown_config)
+                .expect("Render code block failed.");
+            return Visiting::Event(Event::Html(html.into()));
         }
 
         Visiting::NotChanged
     }
 
+    fn visit_html(&mut self, html: &CowStr<'a>) -> Visiting {
+        if let Some(heading) = self.heading.as_mut() {
+            // Such as the math in heading.
+            heading.push_event(Event::Html(html.to_owned()));
+            return Visiting::Ignore;
+        }
+
+        if self.processing_image {
+            return Visiting::Ignore;
+        }
+        Visiting::NotChanged
+    }
+
     fn visit_code(&mut self, code: &CowStr<'a>) -> Visiting {
         if let Some(heading) = self.heading.as_mut() {
             heading
@@ -317,3 +405,10 @@ impl Visiting {
         }
     }
 }
+
+// The count of the outer blockquotes of the blockquote starts at `offset`,
+// which are the `>` markers before it in the same line.
+fn quote_depth(markdown: &str, offset: usize) -> usize {
+    let line_start = markdown[..offset].rfind('\n').map_or(0, |index| index + 1);
+    markdown[line_start..offset].matches('>').count()
+}
@@ -0,0 +1,372 @@
+use std::{borrow::Cow, collections::HashMap, ops::Range};
+
+use anyhow::{bail, ensure, Result};
+use pulldown_cmark::{escape::escape_html, Event};
+use tera::Context;
+
+use crate::engine;
+
+use super::inline::{line_column, replace_inline_spans, text_ranges, InlineSpan};
+
+const OPENING: &str = "{{<";
+const CLOSING: &str = ">}}";
+
+/// An inline shortcode in the markdown source.
+///
+/// ```markdown
+/// Press {{< kbd "Ctrl+C" >}} to copy the {{< abbr "URL" "Uniform Resource Locator" >}}.
+/// ```
+///
+/// The shortcode is rendered by the project template `shortcodes/<name>.jinja` if exists,
+/// which receives the positional `args` and named `options` as context,
+/// otherwise by the builtin shortcodes: `kbd`, `abbr` and `badge`.
+#[derive(Debug, PartialEq, Eq)]
+struct Shortcode<'a> {
+    name: &'a str,
+    args: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl<'a> Shortcode<'a> {
+    fn parse(input: &'a str) -> Result<Self> {
+        let mut tokens = Tokenizer { input, offset: 0 };
+        let name = match tokens.next().transpose()? {
+            // The name can't be quoted.
+            Some(Token::Arg(Cow::Borrowed(name))) if is_name(name) => name,
+            _ => bail!("missing shortcode name"),
+        };
+
+        let mut shortcode = Shortcode {
+            name,
+            args: Vec::new(),
+            options: HashMap::new(),
+        };
+        for token in tokens {
+            match token? {
+                Token::Arg(arg) => shortcode.args.push(arg.into_owned()),
+                Token::Option(key, value) => {
+                    shortcode.options.insert(key.to_owned(), value.into_owned());
+                }
+            }
+        }
+        Ok(shortcode)
+    }
+
+    fn render(&self) -> Result<String> {
+        let template = format!("shortcodes/{}.jinja", self.name);
+        if engine::has_template(&template) {
+            let mut context = Context::new();
+            context.insert("args", &self.args);
+            context.insert("options", &self.options);
+            let html = engine::get_tera().render(&template, &context)?;
+            return Ok(html);
+        }
+
+        let mut html = String::new();
+        match self.name {
+            // Each key of the combination is nested, such as `<kbd><kbd>Ctrl</kbd>+<kbd>C</kbd></kbd>`.
+            "kbd" => {
+                let keys = self.required_arg(0, "key")?.split('+').collect::<Vec<_>>();
+                if keys.len() > 1 {
+                    html.push_str("<kbd>");
+                }
+                for (i, key) in keys.iter().enumerate() {
+                    if i > 0 {
+                        html.push('+');
+                    }
+                    html.push_str("<kbd>");
+                    escape_html(&mut html, key.trim())?;
+                    html.push_str("</kbd>");
+                }
+                if keys.len() > 1 {
+                    html.push_str("</kbd>");
+                }
+            }
+            "abbr" => {
+                let abbr = self.required_arg(0, "abbreviation")?;
+                let Some(title) = self.option_or_arg("title", 1) else {
+                    bail!("missing the title of abbreviation `{abbr}`");
+                };
+                html.push_str(r#"<abbr title=""#);
+                escape_html(&mut html, title)?;
+                html.push_str(r#"">"#);
+                escape_html(&mut html, abbr)?;
+                html.push_str("</abbr>");
+            }
+            "badge" => {
+                let text = self.required_arg(0, "text")?;
+                html.push_str(r#"<span class="badge"#);
+                if let Some(color) = self.option_or_arg("color", 1) {
+                    ensure!(
+                        is_name(color),
+                        "invalid badge color `{color}`, only alphanumeric, `-` and `_` are allowed"
+                    );
+                    html.push_str(" badge-");
+                    html.push_str(color);
+                }
+                html.push_str(r#"">"#);
+                escape_html(&mut html, text)?;
+                html.push_str("</span>");
+            }
+            name => bail!("unknown shortcode `{name}`"),
+        }
+        Ok(html)
+    }
+
+    fn required_arg(&self, index: usize, name: &str) -> Result<&str> {
+        match self.args.get(index) {
+            Some(arg) => Ok(arg),
+            None => bail!("missing the {name} of shortcode `{}`", self.name),
+        }
+    }
+
+    fn option_or_arg(&self, key: &str, index: usize) -> Option<&str> {
+        self.options
+            .get(key)
+            .or_else(|| self.args.get(index))
+            .map(String::as_str)
+    }
+}
+
+enum Token<'a> {
+    // The positional argument, such as `"Ctrl+C"`.
+    Arg(Cow<'a, str>),
+    // The named argument, such as `color="red"`.
+    Option(&'a str, Cow<'a, str>),
+}
+
+// Split the shortcode by whitespace, the quoted value can contain whitespace
+// and the escaped quote `\"`.
+struct Tokenizer<'a> {
+    input: &'a str,
+    offset: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn value(&mut self) -> Result<Cow<'a, str>> {
+        let rest = &self.input[self.offset..];
+        let Some(quoted) = rest.strip_prefix('"') else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            self.offset += end;
+            return Ok(Cow::Borrowed(&rest[..end]));
+        };
+
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.offset += i + 2;
+                    return Ok(Cow::Owned(value));
+                }
+                '\\' => match chars.next() {
+                    Some((_, c)) => value.push(c),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        bail!("unterminated quote: {}", rest)
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Token<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.input[self.offset..];
+        self.offset += rest.len() - rest.trim_start().len();
+        let rest = &self.input[self.offset..];
+        if rest.is_empty() {
+            return None;
+        }
+
+        let key_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '"')
+            .unwrap_or(rest.len());
+        if key_end > 0 && rest[key_end..].starts_with('=') {
+            let key = &rest[..key_end];
+            self.offset += key_end + 1;
+            return Some(self.value().map(|value| Token::Option(key, value)));
+        }
+        Some(self.value().map(Token::Arg))
+    }
+}
+
+/// Replace the shortcodes of the markdown events with the rendered html.
+///
+/// The invalid shortcode is reported with its location and rendered as code.
+pub fn render_shortcode_events<'a>(
+    markdown: &'a str,
+    events: impl Iterator<Item = (Event<'a>, Range<usize>)>,
+) -> impl Iterator<Item = (Event<'a>, Range<usize>)> {
+    let spans = find_shortcodes(markdown)
+        .into_iter()
+        .map(|(range, inner)| {
+            let html = Shortcode::parse(inner)
+                .and_then(|shortcode| shortcode.render())
+                .unwrap_or_else(|err| {
+                    let (line, column) = line_column(markdown, range.start);
+                    println!(
+                        "Warning: invalid shortcode at line {line}, column {column} (offset {}): {err:#}",
+                        range.start
+                    );
+                    let mut html = String::from(r#"<code class="shortcode-error">"#);
+                    escape_html(&mut html, &markdown[range.clone()])
+                        .expect("Escape shortcode failed.");
+                    html.push_str("</code>");
+                    html
+                });
+            InlineSpan { range, html }
+        })
+        .collect();
+    replace_inline_spans(markdown, events, spans)
+}
+
+/// Replace the shortcodes with their first argument, which is readable as plain text.
+pub fn strip_shortcodes(markdown: &str) -> Cow<'_, str> {
+    let shortcodes = find_shortcodes(markdown);
+    if shortcodes.is_empty() {
+        return Cow::Borrowed(markdown);
+    }
+
+    let mut stripped = String::new();
+    let mut cursor = 0;
+    for (range, inner) in shortcodes {
+        stripped.push_str(&markdown[cursor..range.start]);
+        if let Some(arg) = Shortcode::parse(inner)
+            .ok()
+            .and_then(|shortcode| shortcode.args.into_iter().next())
+        {
+            stripped.push_str(&arg);
+        }
+        cursor = range.end;
+    }
+    stripped.push_str(&markdown[cursor..]);
+    Cow::Owned(stripped)
+}
+
+// Find the shortcodes and their inner source, the delimiters must be in the text,
+// not in code or html.
+fn find_shortcodes(markdown: &str) -> Vec<(Range<usize>, &str)> {
+    if !markdown.contains(OPENING) {
+        return Vec::new();
+    }
+
+    let text_ranges = text_ranges(markdown);
+    let is_text = |offset: usize| text_ranges.iter().any(|range| range.contains(&offset));
+    let mut shortcodes = Vec::new();
+    let mut offset = 0;
+    while let Some(index) = markdown[offset..].find(OPENING) {
+        let start = offset + index;
+        offset = start + OPENING.len();
+        if !is_text(start) {
+            continue;
+        }
+        // The shortcode can't cross paragraphs. Check the last `}` of the closing,
+        // since `{{<kbd C>}}` contains the inline html `<kbd C>`.
+        let Some(end) = markdown[offset..]
+            .find(CLOSING)
+            .map(|index| offset + index)
+            .filter(|end| {
+                !markdown[start..*end].contains("\n\n") && is_text(*end + CLOSING.len() - 1)
+            })
+        else {
+            continue;
+        };
+        shortcodes.push((start..end + CLOSING.len(), &markdown[offset..end]));
+        offset = end + CLOSING.len();
+    }
+    shortcodes
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use pulldown_cmark::{html, Parser};
+    use test_case::test_case;
+
+    use super::{find_shortcodes, render_shortcode_events, strip_shortcodes, Shortcode};
+
+    #[test_case(r#"{{< kbd "Ctrl+C" >}}"#, &[r#" kbd "Ctrl+C" "#]; "kbd")]
+    #[test_case("a {{< abbr HTML >}} b {{<badge New>}}", &[" abbr HTML ", "badge New"]; "multiple")]
+    #[test_case("`{{< kbd C >}}`\n```\n{{< kbd C >}}\n```", &[]; "code")]
+    #[test_case("{{< kbd C\n\n>}}", &[]; "cross paragraphs")]
+    #[test_case("{{< kbd C", &[]; "unclosed")]
+    fn test_find_shortcodes(markdown: &str, expected: &[&str]) {
+        let shortcodes = find_shortcodes(markdown)
+            .into_iter()
+            .map(|(_, inner)| inner)
+            .collect::<Vec<_>>();
+        assert_eq!(shortcodes, expected);
+    }
+
+    #[test]
+    fn test_parse_shortcode() {
+        let shortcode =
+            Shortcode::parse(r#" badge "Rust \"2021\"" color=orange  title="A b" "#).unwrap();
+        assert_eq!(shortcode.name, "badge");
+        assert_eq!(shortcode.args, ["Rust \"2021\""]);
+        assert_eq!(shortcode.options["color"], "orange");
+        assert_eq!(shortcode.options["title"], "A b");
+
+        assert!(Shortcode::parse(r#" "kbd" "#).is_err());
+        assert!(Shortcode::parse(r#" kbd "C "#).is_err());
+    }
+
+    #[test_case(r#"kbd "Ctrl + C""#, "<kbd><kbd>Ctrl</kbd>+<kbd>C</kbd></kbd>")]
+    #[test_case("kbd Esc", "<kbd>Esc</kbd>")]
+    #[test_case(
+        r#"abbr HTML "HyperText <Markup> Language""#,
+        r#"<abbr title="HyperText &lt;Markup&gt; Language">HTML</abbr>"#
+    )]
+    #[test_case(
+        r#"abbr CSS title="Cascading Style Sheets""#,
+        r#"<abbr title="Cascading Style Sheets">CSS</abbr>"#
+    )]
+    #[test_case(
+        "badge New color=green",
+        r#"<span class="badge badge-green">New</span>"#
+    )]
+    fn test_render_shortcode(input: &str, expected: &str) {
+        assert_eq!(Shortcode::parse(input).unwrap().render().unwrap(), expected);
+    }
+
+    #[test_case("kbd"; "missing key")]
+    #[test_case("abbr HTML"; "missing title")]
+    #[test_case(r#"badge New color="red green""#; "invalid color")]
+    #[test_case("unknown"; "unknown")]
+    fn test_render_invalid_shortcode(input: &str) {
+        assert!(Shortcode::parse(input).unwrap().render().is_err());
+    }
+
+    #[test]
+    fn test_render_shortcode_events() {
+        let markdown = "Press {{< kbd \"Ctrl+C\" >}}, *then* {{< nope >}}.";
+        let events = render_shortcode_events(markdown, Parser::new(markdown).into_offset_iter());
+        let mut html = String::new();
+        html::push_html(&mut html, events.map(|(event, _)| event));
+        assert_eq!(
+            html,
+            concat!(
+                "<p>Press <kbd><kbd>Ctrl</kbd>+<kbd>C</kbd></kbd>, <em>then</em> ",
+                r#"<code class="shortcode-error">{{&lt; nope &gt;}}</code>.</p>"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_strip_shortcodes() {
+        assert_eq!(
+            strip_shortcodes(r#"Press {{< kbd "Ctrl+C" >}} to copy."#),
+            "Press Ctrl+C to copy."
+        );
+    }
+}
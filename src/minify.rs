@@ -0,0 +1,168 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{anyhow, Result};
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use walkdir::WalkDir;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// The size stats of each kind of minified files, such as `HTML` and `CSS`.
+static SIZE_REPORT: Lazy<Mutex<BTreeMap<&'static str, SizeStats>>> = Lazy::new(Default::default);
+
+#[derive(Debug, Default)]
+struct SizeStats {
+    files: usize,
+    original: usize,
+    minified: usize,
+}
+
+static HTML_CFG: Lazy<minify_html::Cfg> = Lazy::new(|| minify_html::Cfg {
+    // Keep the closing tags, some of our templates rely on them to be styled correctly.
+    keep_closing_tags: true,
+    keep_html_and_head_opening_tags: true,
+    minify_css: true,
+    minify_js: true,
+    ..minify_html::Cfg::spec_compliant()
+});
+
+/// Initialize the minifier, must be called before rendering.
+pub fn init(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    SIZE_REPORT.lock().clear();
+}
+
+/// Whether the build output should be minified.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Minify the rendered `html`.
+///
+/// The whitespaces of `<pre>` blocks (such as the highlighted code) are preserved.
+pub fn minify_html(html: &[u8]) -> Vec<u8> {
+    let minified = minify_html::minify(html, &HTML_CFG);
+    record("HTML", html.len(), minified.len());
+    minified
+}
+
+/// Minify all the css and js files in the `static` directory of `dest`.
+///
+/// The already minified files (such as `medium-zoom.min.js`) are skipped.
+pub fn minify_static_assets(dest: &Path) -> Result<()> {
+    WalkDir::new(dest.join("static"))
+        .into_iter()
+        .par_bridge()
+        .try_for_each(|entry| {
+            let entry = entry?;
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy();
+            if !path.is_file() || file_name.contains(".min.") {
+                return anyhow::Ok(());
+            }
+
+            let (kind, minified) = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("css") => ("CSS", minify_css(&fs::read_to_string(path)?)),
+                Some("js") => ("JS", minify_js(&fs::read(path)?)),
+                _ => return anyhow::Ok(()),
+            };
+            match minified {
+                Ok(minified) => {
+                    record(kind, entry.metadata()?.len() as usize, minified.len());
+                    fs::write(path, minified)?;
+                }
+                // Keep the original file if we failed to minify it.
+                Err(err) => println!("Warning: failed to minify `{}`: {}", path.display(), err),
+            }
+            anyhow::Ok(())
+        })
+}
+
+fn minify_css(css: &str) -> Result<Vec<u8>> {
+    let mut stylesheet =
+        StyleSheet::parse(css, ParserOptions::default()).map_err(|err| anyhow!("{err}"))?;
+    stylesheet.minify(MinifyOptions::default())?;
+    let output = stylesheet.to_css(PrinterOptions {
+        minify: true,
+        ..Default::default()
+    })?;
+    Ok(output.code.into_bytes())
+}
+
+fn minify_js(js: &[u8]) -> Result<Vec<u8>> {
+    let session = minify_js::Session::new();
+    let mut output = Vec::with_capacity(js.len());
+    minify_js::minify(&session, minify_js::TopLevelMode::Global, js, &mut output)
+        .map_err(|err| anyhow!("{err:?}"))?;
+    Ok(output)
+}
+
+fn record(kind: &'static str, original: usize, minified: usize) {
+    let mut report = SIZE_REPORT.lock();
+    let stats = report.entry(kind).or_default();
+    stats.files += 1;
+    stats.original += original;
+    stats.minified += minified;
+}
+
+/// Print the size report of minified files.
+pub fn print_report() {
+    for (kind, stats) in SIZE_REPORT.lock().iter() {
+        let saved = stats.original.saturating_sub(stats.minified);
+        println!(
+            "Minified {} {kind} files: {} -> {} (-{:.1}%)",
+            stats.files,
+            format_size(stats.original),
+            format_size(stats.minified),
+            saved as f64 * 100.0 / stats.original.max(1) as f64,
+        );
+    }
+}
+
+fn format_size(size: usize) -> String {
+    if size < 1024 {
+        format!("{size} B")
+    } else if size < 1024 * 1024 {
+        format!("{:.1} KB", size as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", size as f64 / 1024.0 / 1024.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{format_size, minify_css, minify_html};
+
+    #[test]
+    fn test_minify_html_preserve_pre() {
+        let html = "<html>\n<head></head>\n<body>\n    <p>Hello,   zine</p>\n<pre><code><span>fn</span> main() {\n    println!();\n}</code></pre>\n</body>\n</html>";
+        assert_eq!(
+            String::from_utf8_lossy(&minify_html(html.as_bytes())),
+            "<html><head></head><body><p>Hello, zine</p><pre><code><span>fn</span> main() {\n    println!();\n}</code></pre></body></html>"
+        );
+    }
+
+    #[test]
+    fn test_minify_css() {
+        assert_eq!(
+            minify_css("a {\n  color: #ff0000;\n  margin: 0px;\n}\n").unwrap(),
+            b"a{color:red;margin:0}"
+        );
+    }
+
+    #[test_case(512, "512 B")]
+    #[test_case(2048, "2.0 KB")]
+    #[test_case(3 * 1024 * 1024, "3.0 MB")]
+    fn test_format_size(size: usize, expected: &str) {
+        assert_eq!(format_size(size), expected);
+    }
+}
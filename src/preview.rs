@@ -0,0 +1,239 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use dashmap::DashMap;
+use futures::future::join_all;
+use walkdir::WalkDir;
+
+use crate::{
+    code_blocks,
+    data::{self, UrlPreviewInfo},
+    http, images,
+};
+
+/// Fetch the uncached url previews of the markdown files in the content
+/// and pages directories, so that the site can be built in offline mode.
+///
+/// The images to download, such as the thumbnails of the embeds,
+/// are also downloaded for the cached previews.
+pub async fn fetch_previews<P: AsRef<Path>>(source: P) -> Result<()> {
+    let source = load_root_zine_data(source.as_ref())?;
+    let url_previews = data::read().get_all_previews();
+
+    let urls = preview_urls(&source)?
+        .into_iter()
+        .filter(|(url, download_image)| match url_previews.get(url) {
+            Some(info) => info.needs_image_download(*download_image),
+            None => true,
+        })
+        .collect::<Vec<_>>();
+    if urls.is_empty() {
+        println!("All url previews are cached.");
+        return Ok(());
+    }
+
+    let failed = fetch_into(&url_previews, &urls, false).await;
+    data::export(&source)?;
+
+    println!(
+        "Fetched {} of {} url previews.",
+        urls.len() - failed,
+        urls.len()
+    );
+    if failed > 0 {
+        bail!("Failed to fetch {failed} url previews");
+    }
+    Ok(())
+}
+
+// Locate the root folder of `source` as the build command, then load its zine data.
+fn load_root_zine_data(source: &Path) -> Result<PathBuf> {
+    let (source, zine) = crate::locate_root_zine_folder(fs::canonicalize(source)?)?
+        .context("Failed to find the root zine.toml file")?;
+    http::init(&zine.http_config)?;
+    // The downloaded images are saved into the source directory,
+    // which are copied into the dest directory by the build.
+    images::init(&source, &source, &zine.image_config);
+    data::load(&source);
+    Ok(source)
+}
+
+/// The sorted urls to preview of the markdown files in the content and pages
+/// directories, which have no cached preview.
+pub(crate) fn uncached_preview_urls(source: &Path) -> Result<Vec<String>> {
+    let url_previews = data::read().get_all_previews();
+    Ok(preview_urls(source)?
+        .into_keys()
+        .filter(|url| !url_previews.contains_key(url))
+        .collect())
+}
+
+// The urls to preview of the markdown files in the content and pages directories,
+// and whether to download their images.
+fn preview_urls(source: &Path) -> Result<BTreeMap<String, bool>> {
+    let mut urls = BTreeMap::new();
+    let dirs = [source.join(crate::ZINE_CONTENT_DIR), source.join("pages")];
+    for dir in dirs.iter().filter(|dir| dir.exists()) {
+        for entry in WalkDir::new(dir) {
+            let entry = entry?;
+            if entry.path().extension() != Some(OsStr::new("md")) {
+                continue;
+            }
+            let markdown = fs::read_to_string(entry.path())?;
+            for (url, download_image) in code_blocks::preview_urls(&markdown) {
+                *urls.entry(url).or_default() |= download_image;
+            }
+        }
+    }
+    Ok(urls)
+}
+
+/// Refetch the url previews cached in the `zine-data.json`.
+///
+/// Only refresh the given `url` if specified, and skip the previews
+/// fetched within the `older_than` duration.
+pub async fn refresh_previews<P: AsRef<Path>>(
+    source: P,
+    older_than: Option<Duration>,
+    url: Option<String>,
+) -> Result<()> {
+    let source = load_root_zine_data(source.as_ref())?;
+    let url_previews = data::read().get_all_previews();
+    let is_expired = |info: &UrlPreviewInfo| match older_than {
+        Some(max_age) => info.is_older_than(max_age),
+        None => true,
+    };
+    // Download the image again if the stale preview has a downloaded one,
+    // such as the thumbnail of the embed.
+    let urls = match url {
+        Some(url) => match url_previews.get(&url) {
+            Some(info) if !is_expired(&info) => vec![],
+            Some(info) => vec![(url, info.local_image.is_some())],
+            None => vec![(url, false)],
+        },
+        None => url_previews
+            .iter()
+            .filter(|kv| is_expired(kv.value()))
+            .map(|kv| (kv.key().to_owned(), kv.local_image.is_some()))
+            .collect(),
+    };
+    if urls.is_empty() {
+        println!("No url preview needs to refresh.");
+        return Ok(());
+    }
+
+    // Keep the stale preview if the refetching failed.
+    let failed = fetch_into(&url_previews, &urls, true).await;
+    data::export(&source)?;
+
+    println!(
+        "Refreshed {} of {} url previews.",
+        urls.len() - failed,
+        urls.len()
+    );
+    if failed > 0 {
+        bail!("Failed to refresh {failed} url previews");
+    }
+    Ok(())
+}
+
+// Fetch the previews of the urls into `url_previews`, and download the images
+// if needed, return the failed count.
+//
+// The cached previews are refetched if `refetch` is true,
+// otherwise only their images are downloaded.
+async fn fetch_into(
+    url_previews: &DashMap<String, UrlPreviewInfo>,
+    urls: &[(String, bool)],
+    refetch: bool,
+) -> usize {
+    let results = join_all(urls.iter().map(|(url, download_image)| async move {
+        let cached = url_previews
+            .get(url)
+            .filter(|_| !refetch)
+            .map(|info| info.clone());
+        let mut info = match cached {
+            Some(info) => info,
+            None => UrlPreviewInfo::fetch(url).await?,
+        };
+        if info.needs_image_download(*download_image) {
+            // The preview is still usable if the downloading failed.
+            if let Err(err) = info.download_image().await {
+                println!("Warning: download the image of `{url}` failed: {err}");
+            }
+        }
+        anyhow::Ok(info)
+    }))
+    .await;
+    let mut failed = 0;
+    for ((url, _), result) in urls.iter().zip(results) {
+        match result {
+            Ok(info) => {
+                println!("URL previewed: {url}");
+                url_previews.insert(url.to_owned(), info);
+            }
+            Err(err) => {
+                println!("Warning: preview `{url}` failed: {err}");
+                failed += 1;
+            }
+        }
+    }
+    failed
+}
+
+/// Parse the duration such as `90s`, `30m`, `12h`, `30d` and `2w`.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let unit_index = input
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("Missing the time unit of `{input}`, such as `30d`"))?;
+    let (value, unit) = input.split_at(unit_index);
+    let value = value
+        .parse::<u64>()
+        .map_err(|_| format!("Invalid duration `{input}`"))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "Unknown time unit `{unit}`, expect s, m, h, d or w"
+            ))
+        }
+    };
+    Ok(Duration::from_secs(value * seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use test_case::test_case;
+
+    use super::parse_duration;
+
+    #[test_case("90s", 90)]
+    #[test_case("30m", 30 * 60)]
+    #[test_case("12h", 12 * 60 * 60)]
+    #[test_case("30d", 30 * 24 * 60 * 60)]
+    #[test_case(" 2w", 14 * 24 * 60 * 60)]
+    fn test_parse_duration(input: &str, seconds: u64) {
+        assert_eq!(parse_duration(input), Ok(Duration::from_secs(seconds)));
+    }
+
+    #[test_case("30"; "no unit")]
+    #[test_case("d"; "no value")]
+    #[test_case("30y"; "unknown unit")]
+    #[test_case("1.5d"; "float")]
+    fn test_parse_invalid_duration(input: &str) {
+        assert!(parse_duration(input).is_err());
+    }
+}
@@ -20,7 +20,7 @@ use tower_http::services::ServeDir;
 // The temporal build dir, mainly for `zine serve` command.
 static TEMP_ZINE_BUILD_DIR: &str = "__zine_build";
 
-pub async fn run_serve(source: String, port: u16) -> Result<()> {
+pub async fn run_serve(source: String, port: u16, compress: bool, offline: bool) -> Result<()> {
     let tmp_dir = env::temp_dir().join(TEMP_ZINE_BUILD_DIR);
     if tmp_dir.exists() {
         // Remove cached build directory to invalidate the old cache.
@@ -29,10 +29,23 @@ pub async fn run_serve(source: String, port: u16) -> Result<()> {
 
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
     let (tx, _rx) = broadcast::channel(64);
-    let serve_dir = ServeDir::new(&tmp_dir).fallback(FallbackService { tx: tx.clone() });
+    // Serve the precompressed files if the client accepts the encoding.
+    let serve_dir = ServeDir::new(&tmp_dir)
+        .precompressed_br()
+        .precompressed_gzip()
+        .fallback(FallbackService { tx: tx.clone() });
 
     tokio::spawn(async move {
-        match watch_build(Path::new(&source), tmp_dir.as_path(), true, Some(tx)).await {
+        match watch_build(
+            Path::new(&source),
+            tmp_dir.as_path(),
+            true,
+            compress,
+            offline,
+            Some(tx),
+        )
+        .await
+        {
             Ok(result) => result,
             Err(e) => {
                 // handle the error here, for example by logging it or returning it to the caller
@@ -0,0 +1,203 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{entity::Theme, helpers};
+
+/// The convention name of the theme packages directory of the project.
+static THEMES_DIR: &str = "themes";
+/// The manifest file of a theme package.
+static THEME_FILE: &str = "theme.toml";
+/// The directory (relative to the cache directory) of extracted theme archives.
+static CACHE_DIR: &str = "themes";
+
+/// A theme package, which is a directory (or a `.zip` archive) contains:
+///
+/// - `theme.toml`, the default [`Theme`] config in the `[theme]` table.
+/// - `templates/`, the templates to override the builtin templates.
+/// - `static/`, the static assets.
+/// - `locales/`, the translation files.
+///
+/// The project's own templates, static assets and translations take precedence
+/// over the theme package's.
+#[derive(Debug, Clone)]
+pub struct ThemePackage {
+    dir: PathBuf,
+}
+
+impl ThemePackage {
+    /// Locate the theme package `name` of the project `source`.
+    ///
+    /// The `name` is either a local path relative to the project,
+    /// or a package name in the `themes` directory of the project.
+    pub fn locate(source: &Path, name: &str) -> Result<Self> {
+        let path = [source.join(name), source.join(THEMES_DIR).join(name)]
+            .into_iter()
+            .find(|path| path.exists())
+            .with_context(|| {
+                format!("Theme package `{name}` not found, neither `{name}` nor `{THEMES_DIR}/{name}` exists")
+            })?;
+
+        let dir = if path.is_file() {
+            extract_archive(source, &path)?
+        } else {
+            path
+        };
+        Ok(ThemePackage {
+            dir: find_package_root(&dir)
+                .with_context(|| format!("Invalid theme package `{}`", dir.display()))?,
+        })
+    }
+
+    /// The root directory of the theme package.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Merge the project's `[theme]` table over the `[theme]` table of `theme.toml`,
+    /// then parse the merged [`Theme`].
+    pub fn merge_theme(&self, project_theme: Option<&toml::Value>) -> Result<Theme> {
+        let file = self.dir.join(THEME_FILE);
+        let manifest = toml::from_str::<toml::Value>(&fs::read_to_string(&file)?)
+            .with_context(|| format!("Failed to parse `{}`", file.display()))?;
+
+        let mut table = manifest
+            .get("theme")
+            .and_then(toml::Value::as_table)
+            .cloned()
+            .unwrap_or_default();
+        // The template paths of theme package are relative to the package directory.
+        for key in [
+            "head_template",
+            "footer_template",
+            "article_extend_template",
+        ] {
+            if let Some(toml::Value::String(path)) = table.get_mut(key) {
+                *path = self.dir.join(&*path).display().to_string();
+            }
+        }
+        if let Some(project_table) = project_theme.and_then(toml::Value::as_table) {
+            table.extend(project_table.clone());
+        }
+
+        let mut theme = toml::Value::Table(table).try_into::<Theme>()?;
+        theme.package = Some(self.clone());
+        Ok(theme)
+    }
+}
+
+// Extract the theme archive into the cache directory, return the extracted directory.
+fn extract_archive(source: &Path, archive: &Path) -> Result<PathBuf> {
+    let is_zip = archive.extension().is_some_and(|ext| ext == "zip");
+    if !is_zip {
+        bail!(
+            "Unsupported theme archive `{}`, only `.zip` archive is supported",
+            archive.display()
+        );
+    }
+
+    let bytes = fs::read(archive)?;
+    let stem = archive
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    // Extract again only if the archive has changed.
+    let dir = source
+        .join(crate::ZINE_CACHE_DIR)
+        .join(CACHE_DIR)
+        .join(format!("{stem}-{}", helpers::content_hash(&bytes)));
+    if !dir.exists() {
+        // Extract to a temporary directory first, avoid leaving a broken package in the cache.
+        let tmp_dir = dir.with_extension("tmp");
+        if tmp_dir.exists() {
+            fs::remove_dir_all(&tmp_dir)?;
+        }
+        zip::ZipArchive::new(Cursor::new(bytes))?
+            .extract(&tmp_dir)
+            .with_context(|| format!("Failed to extract `{}`", archive.display()))?;
+        fs::rename(tmp_dir, &dir)?;
+    }
+    Ok(dir)
+}
+
+// Find the directory contains `theme.toml`, which is either `dir` itself
+// or the only child directory of `dir` (the archive usually has a top level directory).
+fn find_package_root(dir: &Path) -> Result<PathBuf> {
+    if dir.join(THEME_FILE).is_file() {
+        return Ok(dir.to_path_buf());
+    }
+
+    let children = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match children.as_slice() {
+        [child] if child.join(THEME_FILE).is_file() => Ok(child.clone()),
+        _ => bail!("`{THEME_FILE}` not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write};
+
+    use super::ThemePackage;
+
+    #[test]
+    fn test_theme_package() {
+        let source = tempfile::tempdir().unwrap();
+        let source = source.path();
+        let package_dir = source.join("themes/paper");
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(
+            package_dir.join("theme.toml"),
+            "[theme]\nprimary_color = \"#000000\"\nlink_color = \"#111111\"\nfooter_template = \"footer.html\"\n",
+        )
+        .unwrap();
+
+        let package = ThemePackage::locate(source, "paper").unwrap();
+        assert_eq!(package.dir(), package_dir);
+
+        let project_theme =
+            toml::from_str::<toml::Value>("[theme]\nname = \"paper\"\nlink_color = \"#222222\"\n")
+                .unwrap();
+        let theme = package.merge_theme(project_theme.get("theme")).unwrap();
+        assert_eq!(theme.name.as_deref(), Some("paper"));
+        assert_eq!(theme.primary_color, "#000000");
+        assert_eq!(theme.link_color, "#222222");
+        assert_eq!(theme.secondary_color, "#eff3f7");
+        assert_eq!(
+            theme.footer_template,
+            Some(package_dir.join("footer.html").display().to_string())
+        );
+
+        assert!(ThemePackage::locate(source, "missing").is_err());
+    }
+
+    #[test]
+    fn test_theme_archive() {
+        let source = tempfile::tempdir().unwrap();
+        let source = source.path();
+
+        let mut writer = zip::ZipWriter::new(fs::File::create(source.join("paper.zip")).unwrap());
+        writer
+            .start_file("paper/theme.toml", Default::default())
+            .unwrap();
+        writer
+            .write_all(b"[theme]\nmain_color = \"#fafafa\"\n")
+            .unwrap();
+        writer
+            .start_file("paper/templates/article.jinja", Default::default())
+            .unwrap();
+        writer.write_all(b"article").unwrap();
+        writer.finish().unwrap();
+
+        let package = ThemePackage::locate(source, "paper.zip").unwrap();
+        assert!(package.dir().ends_with("paper"));
+        assert!(package.dir().join("templates/article.jinja").is_file());
+        assert_eq!(package.merge_theme(None).unwrap().main_color, "#fafafa");
+    }
+}
@@ -12,7 +12,7 @@
         };
     }
 
-    mediumZoom(document.querySelectorAll('.prose p>img'), {
+    mediumZoom(document.querySelectorAll('.prose p img'), {
         margin: 24,
         background: '#000C',
     });
@@ -58,6 +58,11 @@
         highlightToc();
     }
 
+    setupThemeToggle();
+    setupCodeCopy();
+    setupEmbeds();
+    setupTabs();
+
     function dismissInTimeout(element) {
         return setTimeout(() => {
             element.classList.add('hidden');
@@ -111,4 +116,85 @@
             list.classList.add('hidden');
         });
     }
+
+    function setupCodeCopy() {
+        for (let button of document.querySelectorAll('.code-block-copy')) {
+            button.onclick = () => {
+                // The line numbers are CSS generated content, they are excluded from the text.
+                let code = button.parentElement.querySelector('pre').textContent;
+                navigator.clipboard.writeText(code).then(() => {
+                    button.classList.add('copied');
+                    setTimeout(() => button.classList.remove('copied'), 1500);
+                });
+            };
+        }
+    }
+
+    function setupEmbeds() {
+        for (let facade of document.querySelectorAll('.embed-facade')) {
+            // Load the third-party player only when the reader clicks.
+            facade.onclick = (event) => {
+                event.preventDefault();
+                let iframe = document.createElement('iframe');
+                if (facade.dataset.src) {
+                    iframe.src = facade.dataset.src;
+                } else {
+                    iframe.srcdoc = facade.dataset.srcdoc;
+                }
+                iframe.title = facade.dataset.title;
+                iframe.className = 'embed-frame';
+                iframe.allow = 'autoplay; encrypted-media; fullscreen; picture-in-picture';
+                iframe.allowFullscreen = true;
+                facade.replaceWith(iframe);
+            };
+        }
+    }
+
+    function setupTabs() {
+        for (let tabList of document.querySelectorAll('.tabs-list')) {
+            let tabs = Array.from(tabList.querySelectorAll('[role="tab"]'));
+            let select = (tab) => {
+                for (let other of tabs) {
+                    let selected = other === tab;
+                    other.setAttribute('aria-selected', selected);
+                    other.tabIndex = selected ? 0 : -1;
+                    document.getElementById(other.getAttribute('aria-controls')).hidden = !selected;
+                }
+                tab.focus();
+            };
+            tabs.forEach((tab, index) => {
+                tab.onclick = () => select(tab);
+                // Move between the tabs with the arrow keys, as the WAI-ARIA tabs pattern.
+                tab.onkeydown = (event) => {
+                    let offset = { ArrowLeft: -1, ArrowRight: 1 }[event.key];
+                    if (event.key === 'Home') {
+                        select(tabs[0]);
+                    } else if (event.key === 'End') {
+                        select(tabs[tabs.length - 1]);
+                    } else if (offset) {
+                        select(tabs[(index + offset + tabs.length) % tabs.length]);
+                    } else {
+                        return;
+                    }
+                    event.preventDefault();
+                };
+            });
+        }
+    }
+
+    function setupThemeToggle() {
+        let toggle = document.getElementById('zine-theme-toggle');
+        if (!toggle) return;
+
+        toggle.onclick = () => {
+            let root = document.documentElement;
+            // Follow the system color scheme if the reader never toggled.
+            let isDark = root.dataset.theme
+                ? root.dataset.theme === 'dark'
+                : window.matchMedia('(prefers-color-scheme: dark)').matches;
+            let theme = isDark ? 'light' : 'dark';
+            root.dataset.theme = theme;
+            localStorage.setItem('zine-theme', theme);
+        };
+    }
 })();
\ No newline at end of file